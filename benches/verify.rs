@@ -0,0 +1,73 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rusted_nostr_tools::{
+    event_builder::EventBuilder,
+    event_methods::{verify_events, SignedEvent},
+    keys::Keys,
+};
+use secp256k1::{Message, Secp256k1};
+
+fn events(count: usize) -> Vec<SignedEvent> {
+    let key = Keys::generate();
+
+    (0..count)
+        .map(|i| {
+            EventBuilder::text_note(&format!("event {}", i))
+                .tag(["t", "bench"])
+                .custom_created_at(1_700_000_000 + i as i64)
+                .build(&key)
+                .unwrap()
+        })
+        .collect()
+}
+
+fn verify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verify");
+
+    for count in [100, 1_000, 10_000] {
+        let events = events(count);
+        group.throughput(Throughput::Elements(count as u64));
+
+        // A new context for every signature, as verify_signature used to do
+        group.bench_with_input(
+            BenchmarkId::new("fresh_context", count),
+            &events,
+            |b, events| {
+                b.iter(|| {
+                    for event in events {
+                        event.verify_id().unwrap();
+                        let secp = Secp256k1::verification_only();
+                        let message = Message::from_slice(event.id.as_bytes()).unwrap();
+                        secp.verify_schnorr(
+                            &event.sig.schnorr_signature(),
+                            &message,
+                            &event.pubkey.x_only_public_key(),
+                        )
+                        .unwrap();
+                    }
+                })
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("per_event", count),
+            &events,
+            |b, events| {
+                b.iter(|| {
+                    for event in events {
+                        event.verify().unwrap();
+                    }
+                })
+            },
+        );
+
+        // Runs on the rayon thread pool with `--features rayon`
+        group.bench_with_input(BenchmarkId::new("batch", count), &events, |b, events| {
+            b.iter(|| verify_events(events))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, verify);
+criterion_main!(benches);
@@ -1,13 +1,21 @@
+use super::error::Error;
+use super::event_builder::EventBuilder;
 use super::event_methods::SignedEvent;
-use serde_json::{json, Value};
+use super::relay_pool::{RelayOptions, RelayPool};
+use super::signer::NostrSigner;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
-use tungstenite::Message;
+use tokio::sync::{broadcast, watch};
+use tokio::time::Instant;
 
 use crate::websocket::{
+    client_message::ClientMessage,
+    relay::{Notification, ReconnectOptions, RelayStatus},
+    relay_message::{MachineReadablePrefix, RelayMessage, RelayMessageError},
     req::{Req, ReqFilter},
-    ws::{SimplifiedWS, SimplifiedWSError},
+    ws::SimplifiedWSError,
 };
 
 #[derive(Error, Debug)]
@@ -23,6 +31,25 @@ pub enum ClientError {
 
     #[error("Serde Error: {}", _0)]
     SerdeError(#[from] serde_json::Error),
+
+    #[error("Relay Message Error: {}", _0)]
+    RelayMessageError(#[from] RelayMessageError),
+
+    #[error("Only {accepted} of the required {required} relays accepted the event")]
+    QuorumNotReached {
+        required: usize,
+        accepted: usize,
+        statuses: HashMap<String, PublishStatus>,
+    },
+
+    #[error("Missed {0} relay messages, the results would be incomplete")]
+    Lagged(u64),
+
+    #[error("No signer was set on the client")]
+    SignerNotConfigured,
+
+    #[error(transparent)]
+    Nostr(#[from] Error),
 }
 
 impl From<SimplifiedWSError> for ClientError {
@@ -31,16 +58,91 @@ impl From<SimplifiedWSError> for ClientError {
     }
 }
 
+/// Options used when publishing an event and waiting for the relays' `OK` messages
+#[derive(Debug, Clone)]
+pub struct PublishOptions {
+    /// How long to wait for the relays to answer
+    pub timeout: Duration,
+    /// Return as soon as this many relays accepted the event, fail if fewer did before the timeout.
+    /// A quorum of 0 returns right after sending, with every relay still [`PublishStatus::Pending`]
+    pub quorum: Option<usize>,
+    /// Publish to these relays instead of the write relays of the pool
+    pub relays: Option<Vec<String>>,
+}
+
+impl Default for PublishOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            quorum: None,
+            relays: None,
+        }
+    }
+}
+
+/// Outcome of publishing an event to a single relay
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublishStatus {
+    /// The relay answered `OK true`, duplicates are reported with the `Duplicate` prefix
+    Accepted {
+        prefix: Option<MachineReadablePrefix>,
+        message: String,
+    },
+    /// The relay answered `OK false`
+    Rejected {
+        prefix: Option<MachineReadablePrefix>,
+        message: String,
+    },
+    /// The relay did not answer before the quorum was reached
+    Pending,
+    /// The relay did not answer before the timeout
+    TimedOut,
+    /// The relay was removed or gave up reconnecting before answering
+    Terminated,
+}
+
+impl PublishStatus {
+    fn from_ok(status: bool, message: String) -> Self {
+        let prefix = MachineReadablePrefix::parse(&message);
+
+        if status {
+            Self::Accepted { prefix, message }
+        } else {
+            Self::Rejected { prefix, message }
+        }
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted { .. })
+    }
+}
+
 pub struct Client {
-    pub relays: HashMap<String, Arc<tokio::sync::Mutex<SimplifiedWS>>>,
-    pub subscriptions: HashMap<String, Vec<Message>>,
+    pub pool: RelayPool,
+    pub subscriptions: HashMap<String, Vec<SignedEvent>>,
+    /// Options used to reconnect to the relays added after they are set
+    pub reconnect_options: ReconnectOptions,
+    /// Drop the events whose id or signature is invalid in `get_events_of`, enabled by default
+    pub verify_events: bool,
+    /// How long `get_events_of` waits for the relays to send their `EOSE`
+    pub get_events_timeout: Duration,
+    signer: Option<Arc<dyn NostrSigner>>,
+    receiver: broadcast::Receiver<Notification>,
 }
 
 impl Client {
     pub async fn new(default_relays: Vec<&str>) -> Result<Self, ClientError> {
+        let pool = RelayPool::new();
+        let receiver = pool.notifications();
+
         let mut client = Self {
-            relays: HashMap::new(),
+            pool,
             subscriptions: HashMap::new(),
+            reconnect_options: ReconnectOptions::default(),
+            verify_events: true,
+            get_events_timeout: Duration::from_secs(10),
+            signer: None,
+            receiver,
         };
 
         for relay in default_relays {
@@ -52,175 +154,403 @@ impl Client {
 }
 
 impl Client {
+    /// Add a relay used both to read and write
     pub async fn add_relay(&mut self, relay: &str) -> Result<(), ClientError> {
-        let client = match SimplifiedWS::new(relay).await {
-            Ok(client) => client,
-            Err(err) => return Err(ClientError::WSError(err)),
-        };
+        self.add_relay_with_options(relay, RelayOptions::default())
+            .await
+    }
 
-        // Check if relay is already added
-        if self.relays.contains_key(relay) {
-            return Err(ClientError::AlreadySubscribed);
-        }
+    /// Add a relay with the given roles
+    /// # Example
+    /// ```rust
+    /// use rusted_nostr_tools::{client::Client, relay_pool::RelayOptions, test_relay::TestRelay};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let discovery_relay = TestRelay::run().await.unwrap();
+    ///
+    ///     let mut client = Client::new(vec![]).await.unwrap();
+    ///     client
+    ///         .add_relay_with_options(&relay.url(), RelayOptions::default())
+    ///         .await
+    ///         .unwrap();
+    ///     client
+    ///         .add_relay_with_options(
+    ///             &discovery_relay.url(),
+    ///             RelayOptions {
+    ///                 discovery: true,
+    ///                 ..RelayOptions::read_only()
+    ///             },
+    ///         )
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub async fn add_relay_with_options(
+        &mut self,
+        relay: &str,
+        options: RelayOptions,
+    ) -> Result<(), ClientError> {
+        self.pool
+            .add_relay(relay, options, self.reconnect_options.clone())
+            .await
+    }
 
-        self.relays
-            .insert(relay.to_string(), Arc::new(tokio::sync::Mutex::new(client)));
+    pub async fn remove_relay(&mut self, relay: &str) -> Result<(), ClientError> {
+        self.pool.remove_relay(relay).await
+    }
 
-        Ok(())
+    /// Get the connection state of a relay
+    pub fn relay_status(&self, relay: &str) -> Option<RelayStatus> {
+        self.pool.relay(relay).map(|relay| relay.status())
     }
 
-    pub async fn remove_relay(&mut self, relay: &str) -> Result<(), ClientError> {
-        if !self.relays.contains_key(relay) {
-            return Err(ClientError::RelayDoesNotExist);
-        }
+    /// Get a new receiver of the `(relay_url, message)` notifications sent by every relay
+    ///
+    /// Each relay is read by its own background task, the receiver can be moved to another task
+    /// and used in `tokio::select!` without holding on to the client. Frames that could not be
+    /// parsed are received as a [`RelayMessageError`].
+    /// # Example
+    /// ```rust
+    /// use rusted_nostr_tools::{client::Client, relay_message::RelayMessage, test_relay::TestRelay};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let client = Client::new(vec![&relay.url()]).await.unwrap();
+    ///     let mut notifications = client.notifications();
+    ///
+    ///     tokio::spawn(async move {
+    ///         while let Ok((relay_url, message)) = notifications.recv().await {
+    ///             if let Ok(RelayMessage::Notice { message }) = message {
+    ///                 println!("Notice from {}: {}", relay_url, message);
+    ///             }
+    ///         }
+    ///     });
+    /// }
+    /// ```
+    pub fn notifications(&self) -> broadcast::Receiver<Notification> {
+        self.pool.notifications()
+    }
 
-        // Close the connection
-        self.relays
-            .remove(relay)
-            .unwrap()
-            .lock()
-            .await
-            .socket
-            .close(None)
-            .await
-            .unwrap();
+    /// Set the signer used to sign the events built by the client
+    pub fn set_signer(&mut self, signer: Arc<dyn NostrSigner>) {
+        self.signer = Some(signer);
+    }
 
-        Ok(())
+    pub fn signer(&self) -> Option<Arc<dyn NostrSigner>> {
+        self.signer.clone()
+    }
+
+    /// Sign an event with the signer of the client
+    pub async fn sign_event_builder(
+        &self,
+        builder: EventBuilder,
+    ) -> Result<SignedEvent, ClientError> {
+        let signer = self
+            .signer
+            .as_ref()
+            .ok_or(ClientError::SignerNotConfigured)?;
+
+        Ok(builder.sign(signer).await?)
     }
 
-    /// Publish a Nostr event
+    /// Sign an event with the signer of the client and publish it to the write relays
+    /// # Example
+    /// ```rust
+    /// use rusted_nostr_tools::{
+    ///     client::Client, event_builder::EventBuilder, keys::Keys, test_relay::TestRelay,
+    /// };
+    /// use std::sync::Arc;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let mut client = Client::new(vec![&relay.url()]).await.unwrap();
+    ///     client.set_signer(Arc::new(Keys::generate()));
+    ///
+    ///     let event = client
+    ///         .publish_event_builder(EventBuilder::text_note("hello"))
+    ///         .await
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub async fn publish_event_builder(
+        &mut self,
+        builder: EventBuilder,
+    ) -> Result<SignedEvent, ClientError> {
+        let event = self.sign_event_builder(builder).await?;
+        self.publish_event(&event).await?;
+
+        Ok(event)
+    }
+
+    /// Publish a Nostr event to the write relays
     pub async fn publish_event(&mut self, event: &SignedEvent) -> Result<(), ClientError> {
-        let json_stringified = json!(["EVENT", event]).to_string();
-        let message = Message::text(json_stringified);
+        let relays = self.pool.write_relays();
+        self.publish_event_to(&relays, event).await
+    }
 
-        for relay in self.relays.values() {
-            let mut relay = relay.lock().await;
-            relay.send_message(&message).await?;
-        }
+    /// Publish a Nostr event to the given relays only
+    pub async fn publish_event_to<S: AsRef<str>>(
+        &mut self,
+        relays: &[S],
+        event: &SignedEvent,
+    ) -> Result<(), ClientError> {
+        self.pool
+            .send_to(relays, ClientMessage::Event(Box::new(event.clone())))?;
 
         Ok(())
     }
 
-    #[cfg(not(feature = "async"))]
-    /// Get next data from the relays
+    /// Publish a Nostr event and wait for the `OK` message of every targeted relay
+    ///
+    /// Relays that are terminated, or become terminated before answering, are reported as
+    /// [`PublishStatus::Terminated`] without waiting for the timeout. Returns
+    /// [`ClientError::Lagged`] if relay messages were dropped, since an `OK` may have been lost.
     /// # Example
-    /// ```rust
-    /// use std::{
-    ///  sync::{Arc, Mutex},
-    ///  thread,
-    /// };
-    /// use tungstenite::Message;
-    /// use nostr_rust::{nostr_client::Client, req::ReqFilter};
+    /// ```rust,no_run
+    /// use rusted_nostr_tools::client::{Client, PublishOptions};
+    /// # use rusted_nostr_tools::event_methods::SignedEvent;
     ///
-    /// fn handle_message(relay_url: &String, message: &Message) -> Result<(), String> {
-    ///   println!("Received message: {:?}", message);
+    /// # async fn example(event: SignedEvent) {
+    /// let mut client = Client::new(vec!["wss://relay.damus.io", "wss://nos.lol"]).await.unwrap();
+    /// let statuses = client
+    ///     .publish_event_with_ack(
+    ///         &event,
+    ///         PublishOptions {
+    ///             quorum: Some(1),
+    ///             ..Default::default()
+    ///         },
+    ///     )
+    ///     .await
+    ///     .unwrap();
     ///
-    ///   Ok(())
+    /// for (relay_url, status) in statuses {
+    ///     println!("{}: {:?}", relay_url, status);
     /// }
+    /// # }
+    /// ```
+    pub async fn publish_event_with_ack(
+        &mut self,
+        event: &SignedEvent,
+        options: PublishOptions,
+    ) -> Result<HashMap<String, PublishStatus>, ClientError> {
+        let relays = match options.relays {
+            Some(relays) => relays,
+            None => self.pool.write_relays(),
+        };
+
+        let mut notifications = self.notifications();
+        let sent = self
+            .pool
+            .send_to(&relays, ClientMessage::Event(Box::new(event.clone())))?;
+
+        let deadline = Instant::now() + options.timeout;
+        let mut statuses: HashMap<String, PublishStatus> = relays
+            .into_iter()
+            .map(|relay| (relay, PublishStatus::Terminated))
+            .collect();
+        let mut waiting_relays: HashMap<String, watch::Receiver<RelayStatus>> = sent
+            .into_iter()
+            .filter_map(|url| {
+                let status = self.pool.relay(&url)?.status_watch();
+                statuses.insert(url.clone(), PublishStatus::Pending);
+                Some((url, status))
+            })
+            .collect();
+
+        loop {
+            let accepted = statuses.values().filter(|s| s.is_accepted()).count();
+            if options.quorum.is_some_and(|quorum| accepted >= quorum) {
+                return Ok(statuses);
+            }
+
+            // Relays that were removed or gave up reconnecting will never answer
+            waiting_relays.retain(|url, status| {
+                let terminated =
+                    status.has_changed().is_err() || *status.borrow() == RelayStatus::Terminated;
+                if terminated {
+                    statuses.insert(url.to_string(), PublishStatus::Terminated);
+                }
+                !terminated
+            });
+            if waiting_relays.is_empty() {
+                break;
+            }
+
+            let status_changed = futures::future::select_all(
+                waiting_relays
+                    .values_mut()
+                    .map(|status| Box::pin(status.changed())),
+            );
+
+            let (relay_name, message) = tokio::select! {
+                notification = notifications.recv() => match notification {
+                    Ok(notification) => notification,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        return Err(ClientError::Lagged(skipped))
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = status_changed => continue,
+                _ = tokio::time::sleep_until(deadline) => break,
+            };
+
+            let status = match message {
+                Ok(RelayMessage::Ok {
+                    event_id,
+                    status,
+                    message,
+                }) if event_id == event.id => PublishStatus::from_ok(status, message),
+                _ => continue,
+            };
+
+            if waiting_relays.remove(&relay_name).is_none() {
+                continue;
+            }
+            statuses.insert(relay_name, status);
+        }
+
+        for status in statuses.values_mut() {
+            if *status == PublishStatus::Pending {
+                *status = PublishStatus::TimedOut;
+            }
+        }
+
+        if let Some(quorum) = options.quorum {
+            let accepted = statuses.values().filter(|s| s.is_accepted()).count();
+
+            return Err(ClientError::QuorumNotReached {
+                required: quorum,
+                accepted,
+                statuses,
+            });
+        }
+
+        Ok(statuses)
+    }
+
+    /// Get next data from the relays
     ///
-    /// let mut client = Arc::new(Mutex::new(Client::new(vec![env!("RELAY_URL")]).unwrap()));
+    /// Waits for the next message from any relay and returns it along with every other message already received.
+    /// Frames that could not be parsed are returned as a [`RelayMessageError`]. Returns
+    /// [`ClientError::Lagged`] if messages were dropped because they were not read fast enough,
+    /// the next call resumes with the oldest message still queued.
+    /// # Example
+    /// ```rust
+    /// use rusted_nostr_tools::{
+    ///     client::Client, relay_message::RelayMessage, req::ReqFilter, test_relay::TestRelay,
+    /// };
     ///
-    /// // Run a new thread to listen
-    /// let nostr_clone = client.clone();
-    /// let nostr_thread = thread::spawn(move || loop {
-    ///    let events = nostr_clone.lock().unwrap().next_data().unwrap();
-    ///    
-    ///   for (relay_url, message) in events.iter() {
-    ///     handle_message(relay_url, message).unwrap();
-    ///   }
-    /// });
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let mut client = Client::new(vec![&relay.url()]).await.unwrap();
     ///
-    /// // Subscribe to the most beautiful Nostr profile event
-    /// client
-    /// .lock()
-    /// .unwrap()
-    /// .subscribe(vec![ReqFilter {
-    ///     ids: None,
-    ///     authors: Some(vec![
-    ///         "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".to_string(),
-    ///     ]),
-    ///     kinds: None,
-    ///     e: None,
-    ///     p: None,
-    ///     since: None,
-    ///     until: None,
-    ///     limit: Some(1),
-    /// }])
-    /// .unwrap();
+    ///     // Subscribe to the most beautiful Nostr profile event
+    ///     client
+    ///         .subscribe(vec![ReqFilter {
+    ///             authors: Some(vec![
+    ///                 "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".parse().unwrap(),
+    ///             ]),
+    ///             limit: Some(1),
+    ///             ..Default::default()
+    ///         }])
+    ///         .await
+    ///         .unwrap();
     ///
-    /// // Wait 3s for the thread to finish
-    /// std::thread::sleep(std::time::Duration::from_secs(3));
+    ///     for (relay_url, message) in client.next_data().await.unwrap() {
+    ///         if let Ok(RelayMessage::Event { event, .. }) = message {
+    ///             println!("Received event from {}: {:?}", relay_url, event);
+    ///         }
+    ///     }
+    /// }
     /// ```
+    pub async fn next_data(&mut self) -> Result<Vec<Notification>, ClientError> {
+        let mut events: Vec<Notification> = Vec::new();
+
+        // Wait for the first message, then take whatever else is already queued
+        match self.receiver.recv().await {
+            Ok(event) => events.push(event),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                return Err(ClientError::Lagged(skipped))
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                return Err(ClientError::WSError(SimplifiedWSError::ReceiveMessageError))
+            }
+        }
 
-    pub async fn next_data(&mut self) -> Result<Vec<(String, tungstenite::Message)>, ClientError> {
-        let mut events: Vec<(String, tungstenite::Message)> = Vec::new();
-
-        for (relay_name, socket) in self.relays.iter() {
-            let message = socket.lock().await.read_message().await?;
-            events.push((relay_name.clone(), message));
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) => events.push(event),
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    return Err(ClientError::Lagged(skipped))
+                }
+                Err(_) => break,
+            }
         }
 
         Ok(events)
     }
 
-    /// Subscribe
+    /// Subscribe on the read relays
     /// # Example
     /// ```rust
-    /// use nostr_rust::{nostr_client::Client, req::ReqFilter};
+    /// use rusted_nostr_tools::{client::Client, req::ReqFilter, test_relay::TestRelay};
     ///
-    /// #[tokio::test]
-    /// async fn test_subscribe() {
-    ///     let mut client = Client::new(vec![env!("RELAY_URL")]).await.unwrap();
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let mut client = Client::new(vec![&relay.url()]).await.unwrap();
     ///     client
     ///     .subscribe(vec![ReqFilter { // None means generate a random ID
-    ///         ids: None,
     ///         authors: Some(vec![
-    ///             "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".to_string(),
+    ///             "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".parse().unwrap(),
     ///         ]),
-    ///         kinds: None,
-    ///         e: None,
-    ///         p: None,
-    ///         since: None,
-    ///         until: None,
     ///         limit: Some(1),
+    ///         ..Default::default()
     ///     }])
     ///     .await
     ///     .unwrap();
     /// }
     /// ```
     pub async fn subscribe(&mut self, filters: Vec<ReqFilter>) -> Result<String, ClientError> {
-        let req = Req::new(None, filters);
-        let message = Message::text(req.to_string());
+        let relays = self.pool.read_relays();
+        self.subscribe_to(&relays, filters).await
+    }
 
-        for relay in self.relays.values() {
-            let mut relay = relay.lock().await;
-            relay.send_message(&message).await?;
-        }
+    /// Subscribe on the given relays only
+    pub async fn subscribe_to<S: AsRef<str>>(
+        &mut self,
+        relays: &[S],
+        filters: Vec<ReqFilter>,
+    ) -> Result<String, ClientError> {
+        let req = Req::new(None, filters);
+        self.pool
+            .send_to(relays, ClientMessage::from(req.clone()))?;
 
         Ok(req.subscription_id)
     }
 
-    /// Subscribe with a specific ID
+    /// Subscribe with a specific ID on the read relays
     ///
     /// # Example
     /// ```rust
-    /// use nostr_rust::{nostr_client::Client, req::ReqFilter};
+    /// use rusted_nostr_tools::{client::Client, req::ReqFilter, test_relay::TestRelay};
     ///
-    /// #[tokio::test]
-    /// async fn test_subscribe_with_id() {
-    ///     let mut client = Client::new(vec![env!("RELAY_URL")]).await.unwrap();
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let mut client = Client::new(vec![&relay.url()]).await.unwrap();
     ///     client
     ///     .subscribe_with_id("my_subscription_id", vec![ReqFilter {
-    ///        ids: None,
     ///        authors: Some(vec![
-    ///          "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".to_string(),
+    ///          "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".parse().unwrap(),
     ///        ]),
-    ///        kinds: None,
-    ///        e: None,
-    ///        p: None,
-    ///        since: None,
-    ///        until: None,
     ///        limit: Some(1),
+    ///        ..Default::default()
     ///     }])
     ///     .await
     ///     .unwrap();
@@ -232,36 +562,28 @@ impl Client {
         filters: Vec<ReqFilter>,
     ) -> Result<(), ClientError> {
         let req = Req::new(Some(subscription_id), filters);
-        let message = Message::text(req.to_string());
-
-        for relay in self.relays.values() {
-            let mut relay = relay.lock().await;
-            relay.send_message(&message).await?;
-        }
+        self.pool
+            .send_to(&self.pool.read_relays(), ClientMessage::from(req))?;
 
         Ok(())
     }
 
-    /// Unsubscribe
+    /// Unsubscribe from every relay
     /// # Example
     /// ```rust
-    /// use nostr_rust::{nostr_client::Client, req::ReqFilter};
+    /// use rusted_nostr_tools::{client::Client, req::ReqFilter, test_relay::TestRelay};
     ///
-    /// #[tokio::test]
-    /// async fn test_unsubscribe() {
-    ///     let mut client = Client::new(vec![env!("RELAY_URL")]).await.unwrap();
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let mut client = Client::new(vec![&relay.url()]).await.unwrap();
     ///     let subscription_id = client
     ///     .subscribe(vec![ReqFilter {
-    ///        ids: None,
     ///       authors: Some(vec![
-    ///            "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".to_string(),
+    ///            "884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".parse().unwrap(),
     ///       ]),
-    ///      kinds: None,
-    ///      e: None,
-    ///      p: None,
-    ///      since: None,
-    ///      until: None,
     ///      limit: Some(1),
+    ///       ..Default::default()
     ///     }])
     ///     .await
     ///     .unwrap();
@@ -269,117 +591,148 @@ impl Client {
     /// }
     /// ```
     pub async fn unsubscribe(&mut self, subscription_id: &str) -> Result<(), ClientError> {
-        let message = Message::text(json!(["CLOSE", subscription_id]).to_string());
-
-        for relay in self.relays.values() {
-            let mut relay = relay.lock().await;
-            relay.send_message(&message).await?;
-        }
+        self.pool.send_to(
+            &self.pool.urls(),
+            ClientMessage::Close {
+                subscription_id: subscription_id.to_string(),
+            },
+        )?;
 
         Ok(())
     }
 
     /// Add event to a subscription
-    pub fn add_event(&mut self, subscription_id: &str, message: Message) {
-        // Check if the subscription exists
-        if !self.subscriptions.contains_key(subscription_id) {
-            self.subscriptions
-                .insert(subscription_id.to_string(), Vec::new());
-        }
-
-        // Check if the message is already in the subscription
-        if !self.subscriptions[subscription_id].contains(&message) {
-            // Add the message to the subscription
-            self.subscriptions
-                .get_mut(subscription_id)
-                .unwrap()
-                .push(message);
+    pub fn add_event(&mut self, subscription_id: &str, event: SignedEvent) {
+        let events = self
+            .subscriptions
+            .entry(subscription_id.to_string())
+            .or_default();
+
+        // Check if the event is already in the subscription
+        if !events.contains(&event) {
+            events.push(event);
         }
     }
 
     /// Get events and remove them from the subscription
-    pub fn get_events(&mut self, subscription_id: &str) -> Option<Vec<Message>> {
+    pub fn get_events(&mut self, subscription_id: &str) -> Option<Vec<SignedEvent>> {
         self.subscriptions.remove(subscription_id)
     }
 
-    /// Get events of a given filters
+    /// Get events of a given filters from the read relays
     ///
+    /// Waits until every read relay sent its `EOSE` or `CLOSED`, or is terminated, for at most
+    /// `get_events_timeout`. Events that do not match the filters are dropped, as are events
+    /// with an invalid id or signature unless `verify_events` is disabled.
     /// # Example
     /// ```rust
-    /// use nostr_rust::{nostr_client::Client, req::ReqFilter};
+    /// use rusted_nostr_tools::{client::Client, kind::Kind, req::ReqFilter, test_relay::TestRelay};
     ///
-    /// #[tokio::test]
-    /// async fn test_get_events_of() {
-    ///     let mut client = Client::new(vec![env!("RELAY_URL")]).await.unwrap();
-    ///     let events = client.get_events_of(vec![ReqFilter {
-    ///        ids: None,
-    ///        authors: Some(vec!["884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6".to_string()]),
-    ///        kinds: Some(vec![3]),
-    ///        e: None,
-    ///        p: None,
-    ///        since: None,
-    ///        until: None,
-    ///        limit: Some(1),
-    ///     }]).await
-    ///     .unwrap();
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let relay = TestRelay::run().await.unwrap();
+    ///     let mut client = Client::new(vec![&relay.url()]).await.unwrap();
+    ///     let filter = ReqFilter::new()
+    ///         .author("884704bd421721e292edbff42eb77547fe115c6ff9825b08fc366be4cd69e9f6")
+    ///         .unwrap()
+    ///         .kind(Kind::ContactList)
+    ///         .limit(1);
+    ///     let events = client.get_events_of(vec![filter]).await.unwrap();
     /// }
     /// ```
     pub async fn get_events_of(
         &mut self,
         filters: Vec<ReqFilter>,
     ) -> Result<Vec<SignedEvent>, ClientError> {
-        let mut events: Vec<SignedEvent> = Vec::new();
-
-        // Subscribe
-        let id = self.subscribe(filters).await?;
-
-        let mut waiting_relays: Vec<String> = self.relays.keys().map(|k| k.to_string()).collect();
-
-        // Get the events
-        while !waiting_relays.is_empty() {
-            let data = self.next_data().await?;
-            let mut break_loop = false;
-
-            for (relay, message) in data {
-                let event: Value = serde_json::from_str(&message.to_string()).unwrap();
-
-                if event[0] == "EOSE" && event[1].as_str() == Some(&id) {
-                    let index = waiting_relays.iter().position(|r| r == &relay).unwrap();
-                    waiting_relays.remove(index);
+        self.get_events_of_with_timeout(filters, self.get_events_timeout)
+            .await
+    }
 
-                    break_loop = true;
-                    break;
-                }
+    /// Get events of a given filters from the read relays, waiting for at most `timeout`
+    ///
+    /// Returns the events received before the timeout, or [`ClientError::Lagged`] if some
+    /// messages of the relays were missed.
+    pub async fn get_events_of_with_timeout(
+        &mut self,
+        filters: Vec<ReqFilter>,
+        timeout: Duration,
+    ) -> Result<Vec<SignedEvent>, ClientError> {
+        let mut notifications = self.notifications();
 
-                self.add_event(&id, message);
+        // Subscribe
+        let id = self.subscribe(filters.clone()).await?;
+
+        let deadline = Instant::now() + timeout;
+        let mut waiting_relays: HashMap<String, watch::Receiver<RelayStatus>> = self
+            .pool
+            .read_relays()
+            .into_iter()
+            .filter_map(|url| {
+                let status = self.pool.relay(&url)?.status_watch();
+                Some((url, status))
+            })
+            .collect();
+        let mut lagged = None;
+
+        // Get the events until every relay sent its EOSE or CLOSED
+        loop {
+            // Relays that were removed or gave up reconnecting will never answer
+            waiting_relays.retain(|_, status| {
+                status.has_changed().is_ok() && *status.borrow() != RelayStatus::Terminated
+            });
+            if waiting_relays.is_empty() {
+                break;
             }
 
-            if break_loop {
-                break;
+            let status_changed = futures::future::select_all(
+                waiting_relays
+                    .values_mut()
+                    .map(|status| Box::pin(status.changed())),
+            );
+
+            let (relay, message) = tokio::select! {
+                notification = notifications.recv() => match notification {
+                    Ok(notification) => notification,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        lagged = Some(skipped);
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = status_changed => continue,
+                _ = tokio::time::sleep_until(deadline) => break,
+            };
+
+            match message {
+                Ok(
+                    RelayMessage::Eose { subscription_id }
+                    | RelayMessage::Closed {
+                        subscription_id, ..
+                    },
+                ) if subscription_id == id => {
+                    waiting_relays.remove(&relay);
+                }
+                // Drop the events the relay should not have sent
+                Ok(RelayMessage::Event {
+                    subscription_id,
+                    event,
+                }) if subscription_id == id
+                    && ReqFilter::matches_any(&filters, &event)
+                    && (!self.verify_events || event.verify().is_ok()) =>
+                {
+                    self.add_event(&id, *event)
+                }
+                _ => {}
             }
         }
 
         // unsubscribe
         self.unsubscribe(&id).await?;
+        let events = self.get_events(&id).unwrap_or_default();
 
-        // Get the events
-        if let Some(messages) = self.get_events(&id) {
-            for message in messages {
-                if !message.is_text() {
-                    continue;
-                }
-
-                let event: Value = serde_json::from_str(&message.to_string())?;
-
-                let event_object = serde_json::from_value::<SignedEvent>(event[2].clone());
-
-                if event_object.is_err() {
-                    continue;
-                }
-
-                events.push(event_object.unwrap());
-            }
+        match lagged {
+            Some(skipped) => Err(ClientError::Lagged(skipped)),
+            None => Ok(events),
         }
-        Ok(events)
     }
 }
@@ -1,33 +1,56 @@
-use bech32::FromBase32;
-
-use super::utils::{bech32_encode, Prefix};
+use super::{
+    error::Error,
+    nip19::Nip19,
+    types::{EventId, ParseError, PublicKey, SecretKey},
+};
 
 pub struct ConvertKey;
 
 impl ConvertKey {
-    pub fn to_hex(key: &str) -> Result<String, String> {
-        let (_, decoded_key, _) = match bech32::decode(key) {
-            Ok((hrp, data, _)) => (hrp, data, true),
-            Err(_) => return Err("Error decoding bech32 key".to_string()),
-        };
+    /// Decode any NIP-19 entity
+    pub fn decode(value: &str) -> Result<Nip19, Error> {
+        Nip19::from_bech32(value)
+    }
+
+    /// Convert a `npub`, `nsec` or `note` to hex, prefer the checked helpers below when the
+    /// expected prefix is known
+    pub fn to_hex(key: &str) -> Result<String, Error> {
+        match Self::decode(key)? {
+            Nip19::Npub(public_key) => Ok(public_key.to_hex()),
+            Nip19::Nsec(secret_key) => Ok(secret_key.to_hex()),
+            Nip19::Note(event_id) => Ok(event_id.to_hex()),
+            entity => Err(ParseError::WrongPrefix {
+                expected: "npub, nsec or note".to_string(),
+                found: entity.hrp().to_string(),
+            }
+            .into()),
+        }
+    }
 
-        let base_32_key = match Vec::<u8>::from_base32(&decoded_key) {
-            Ok(key) => key,
-            Err(_) => return Err("Error converting bech32 key to base32".to_string()),
-        };
+    /// Convert a `npub` to hex, any other prefix is rejected
+    pub fn npub_to_hex(npub: &str) -> Result<String, Error> {
+        Ok(PublicKey::from_bech32(npub)?.to_hex())
+    }
+
+    /// Convert a `nsec` to hex, any other prefix is rejected
+    pub fn nsec_to_hex(nsec: &str) -> Result<String, Error> {
+        Ok(SecretKey::from_bech32(nsec)?.to_hex())
+    }
 
-        let hex_key = hex::encode(base_32_key);
+    /// Convert a `note` to hex, any other prefix is rejected
+    pub fn note_to_hex(note: &str) -> Result<String, Error> {
+        Ok(EventId::from_bech32(note)?.to_hex())
+    }
 
-        Ok(hex_key)
+    pub fn to_bech32_public_key(key: &PublicKey) -> Result<String, Error> {
+        key.to_bech32()
     }
 
-    pub fn to_bech32_public_key(key: &str) -> String {
-        let bech32_pubkey = bech32_encode(Prefix::Npub, &key.to_string());
-        bech32_pubkey
+    pub fn to_bech32_private_key(key: &SecretKey) -> Result<String, Error> {
+        key.to_bech32()
     }
 
-    pub fn to_bech32_private_key(key: &str) -> String {
-        let bech32_privkey = bech32_encode(Prefix::Nsec, &key.to_string());
-        bech32_privkey
+    pub fn to_bech32_note(event_id: &EventId) -> Result<String, Error> {
+        event_id.to_bech32()
     }
 }
@@ -0,0 +1,55 @@
+use thiserror::Error;
+
+use super::{
+    event_methods::VerifyError, nip04::Nip04Error, nip06::Nip06Error, nip19::Nip19Error,
+    nip21::Nip21Error, nip44::Nip44Error, nip59::Nip59Error, types::ParseError,
+};
+
+/// Error returned by the key, encoding and event functions of the crate
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Secp256k1 error: {0}")]
+    Secp256k1(#[from] secp256k1::Error),
+
+    #[error("Hex error: {0}")]
+    Hex(#[from] hex::FromHexError),
+
+    #[error("Bech32 error: {0}")]
+    Bech32(#[from] bech32::Error),
+
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error("Invalid event: {0}")]
+    InvalidEvent(String),
+
+    #[error(transparent)]
+    Nip04(#[from] Nip04Error),
+
+    #[error(transparent)]
+    Nip06(#[from] Nip06Error),
+
+    #[error(transparent)]
+    Nip19(#[from] Nip19Error),
+
+    #[error(transparent)]
+    Nip21(#[from] Nip21Error),
+
+    #[error(transparent)]
+    Nip44(#[from] Nip44Error),
+
+    #[error(transparent)]
+    Nip59(#[from] Nip59Error),
+
+    #[error(transparent)]
+    Verify(#[from] VerifyError),
+
+    #[error("Serde error: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("{0} is not supported by this signer")]
+    Unsupported(&'static str),
+
+    #[error("Signer error: {0}")]
+    Signer(String),
+}
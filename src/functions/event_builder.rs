@@ -0,0 +1,192 @@
+use chrono::Utc;
+use serde_json::Value;
+
+use super::{
+    error::Error,
+    event_methods::{sign_event, SignedEvent, UnsignedEvent},
+    keys::Keys,
+    kind::Kind,
+    signer::NostrSigner,
+    types::{EventId, PublicKey},
+};
+
+/// Entry of a contact list (NIP-02)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contact {
+    pub pubkey: PublicKey,
+    pub relay_url: Option<String>,
+    pub petname: Option<String>,
+}
+
+impl Contact {
+    pub fn new(pubkey: PublicKey) -> Self {
+        Self {
+            pubkey,
+            relay_url: None,
+            petname: None,
+        }
+    }
+
+    fn to_tag(&self) -> Vec<String> {
+        let mut tag = vec!["p".to_string(), self.pubkey.to_hex()];
+
+        if self.relay_url.is_some() || self.petname.is_some() {
+            tag.push(self.relay_url.clone().unwrap_or_default());
+        }
+        if let Some(petname) = &self.petname {
+            tag.push(petname.clone());
+        }
+
+        tag
+    }
+}
+
+/// EventBuilder builds an event, fills its pubkey and created_at and signs it in one step
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{event_builder::EventBuilder, keys::Keys, kind::Kind};
+///
+/// let key = Keys::generate();
+/// let event = EventBuilder::text_note("hello")
+///     .tag(["t", "nostr"])
+///     .custom_created_at(1_700_000_000)
+///     .build(&key)
+///     .unwrap();
+///
+/// assert_eq!(event.kind, Kind::TextNote);
+/// assert_eq!(event.created_at, 1_700_000_000);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventBuilder {
+    kind: Kind,
+    content: String,
+    tags: Vec<Vec<String>>,
+    created_at: Option<i64>,
+}
+
+impl EventBuilder {
+    pub fn new(kind: Kind, content: &str) -> Self {
+        Self {
+            kind,
+            content: content.to_string(),
+            tags: Vec::new(),
+            created_at: None,
+        }
+    }
+
+    /// Short text note (kind 1)
+    pub fn text_note(content: &str) -> Self {
+        Self::new(Kind::TextNote, content)
+    }
+
+    /// Profile metadata (kind 0), e.g. `{"name": ..., "about": ..., "picture": ...}`
+    pub fn metadata(metadata: &Value) -> Self {
+        Self::new(Kind::Metadata, &metadata.to_string())
+    }
+
+    /// Contact list (kind 3, NIP-02)
+    pub fn contact_list(contacts: impl IntoIterator<Item = Contact>) -> Self {
+        Self::new(Kind::ContactList, "").tags(contacts.into_iter().map(|contact| contact.to_tag()))
+    }
+
+    /// Reaction to an event (kind 7, NIP-25), "+" for a like and "-" for a dislike
+    pub fn reaction(event: &SignedEvent, reaction: &str) -> Self {
+        Self::new(Kind::Reaction, reaction)
+            .tag(["e".to_string(), event.id.to_hex()])
+            .tag(["p".to_string(), event.pubkey.to_hex()])
+            .tag(["k".to_string(), event.kind.to_string()])
+    }
+
+    /// Repost of an event (NIP-18), kind 6 for text notes and kind 16 for other kinds
+    pub fn repost(event: &SignedEvent, relay_url: Option<&str>) -> Self {
+        let content = serde_json::to_string(event).unwrap_or_default();
+        let builder = match event.kind {
+            Kind::TextNote => Self::new(Kind::Repost, &content),
+            kind => {
+                Self::new(Kind::GenericRepost, &content).tag(["k".to_string(), kind.to_string()])
+            }
+        };
+
+        builder
+            .tag([
+                "e".to_string(),
+                event.id.to_hex(),
+                relay_url.unwrap_or_default().to_string(),
+            ])
+            .tag(["p".to_string(), event.pubkey.to_hex()])
+    }
+
+    /// NIP-04 encrypted direct message (kind 4) to `recipient`, encrypted and signed by the same
+    /// signer so the recipient can decrypt it with the pubkey of the event
+    pub async fn encrypted_direct_message<S: NostrSigner + ?Sized>(
+        signer: &S,
+        recipient: &PublicKey,
+        content: &str,
+    ) -> Result<SignedEvent, Error> {
+        let content = signer.nip04_encrypt(recipient, content).await?;
+
+        Self::new(Kind::EncryptedDirectMessage, &content)
+            .tag(["p".to_string(), recipient.to_hex()])
+            .sign(signer)
+            .await
+    }
+
+    /// NIP-17 private direct message (kind 14) to `receiver`, it must be gift wrapped with
+    /// [`crate::nip59::gift_wrap`] instead of being signed
+    pub fn private_direct_message(receiver: &PublicKey, message: &str) -> Self {
+        Self::new(Kind::PrivateDirectMessage, message).tag(["p".to_string(), receiver.to_hex()])
+    }
+
+    /// Deletion request of the given event ids (kind 5, NIP-09)
+    pub fn deletion(ids: impl IntoIterator<Item = EventId>, reason: &str) -> Self {
+        Self::new(Kind::EventDeletion, reason)
+            .tags(ids.into_iter().map(|id| vec!["e".to_string(), id.to_hex()]))
+    }
+
+    /// Append a tag, e.g. `["t", "nostr"]`
+    pub fn tag<I, S>(mut self, tag: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags.push(tag.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Append several tags
+    pub fn tags(mut self, tags: impl IntoIterator<Item = Vec<String>>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    /// Use the given timestamp instead of the current time
+    pub fn custom_created_at(mut self, created_at: i64) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Return the event without signing it
+    pub fn to_unsigned_event(&self, pubkey: &PublicKey) -> UnsignedEvent {
+        UnsignedEvent {
+            content: self.content.clone(),
+            created_at: self.created_at.unwrap_or_else(|| Utc::now().timestamp()),
+            kind: self.kind,
+            pubkey: *pubkey,
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Fill the pubkey and created_at of the event and sign it with the given signer
+    pub async fn sign<S: NostrSigner + ?Sized>(self, signer: &S) -> Result<SignedEvent, Error> {
+        let event = self.to_unsigned_event(&signer.get_public_key().await?);
+
+        signer.sign_event(event).await
+    }
+
+    /// Fill the pubkey and created_at of the event and sign it
+    pub fn build(self, keys: &Keys) -> Result<SignedEvent, Error> {
+        let event = self.to_unsigned_event(&keys.public_key());
+
+        sign_event(&event, keys)
+    }
+}
@@ -1,77 +1,188 @@
 use chrono::NaiveDateTime;
-use secp256k1::{
-    schnorr::Signature, Error, KeyPair, Message, Secp256k1, SecretKey, XOnlyPublicKey,
-};
+use secp256k1::{Message, Secp256k1, VerifyOnly};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+use super::{
+    error::Error,
+    keys::Keys,
+    kind::Kind,
+    types::{EventId, PublicKey, Signature},
+};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct UnsignedEvent {
     pub content: String,
     pub created_at: i64,
-    pub kind: u64,
-    pub pubkey: String,
+    pub kind: Kind,
+    pub pubkey: PublicKey,
     pub tags: Vec<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct SignedEvent {
     pub content: String,
     pub created_at: i64,
-    pub id: String,
-    pub kind: u64,
-    pub pubkey: String,
-    pub sig: String,
+    pub id: EventId,
+    pub kind: Kind,
+    pub pubkey: PublicKey,
+    pub sig: Signature,
     pub tags: Vec<Vec<String>>,
 }
 
-pub fn get_event_hash(event: &UnsignedEvent) -> Result<String, String> {
-    let commitment_string = serialize_event(&event)?;
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum VerifyError {
+    #[error("Invalid event: {0}")]
+    InvalidEvent(String),
+
+    #[error("Event id {found} does not match its content, expected {expected}")]
+    IdMismatch { expected: EventId, found: EventId },
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+}
+
+impl SignedEvent {
+    /// Return the event without its id and signature
+    pub fn to_unsigned_event(&self) -> UnsignedEvent {
+        UnsignedEvent {
+            content: self.content.clone(),
+            created_at: self.created_at,
+            kind: self.kind,
+            pubkey: self.pubkey,
+            tags: self.tags.clone(),
+        }
+    }
+
+    /// Recompute the NIP-01 id of the event and compare it to `id`
+    pub fn verify_id(&self) -> Result<(), VerifyError> {
+        let commitment = commitment(
+            &self.pubkey,
+            self.created_at,
+            self.kind,
+            &self.tags,
+            &self.content,
+        )
+        .map_err(|err| VerifyError::InvalidEvent(err.to_string()))?;
+        let expected = EventId::from_bytes(Sha256::digest(commitment.as_bytes()).into());
+
+        if expected != self.id {
+            return Err(VerifyError::IdMismatch {
+                expected,
+                found: self.id,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Check that the id matches the content of the event and that `sig` is a valid signature of it
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        self.verify_id()?;
+
+        verify_signature(&self.sig, &self.pubkey, &self.id)
+            .map_err(|_| VerifyError::InvalidSignature)
+    }
+}
+
+/// Verify many events at once, returns the result of every event in the same order.
+///
+/// The events share a single verification context and are verified on the rayon thread pool
+/// when the `rayon` feature is enabled.
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{event_builder::EventBuilder, event_methods::verify_events, keys::Keys};
+///
+/// let key = Keys::generate();
+/// let events: Vec<_> = (0..10)
+///     .map(|i| EventBuilder::text_note(&i.to_string()).build(&key).unwrap())
+///     .collect();
+///
+/// assert!(verify_events(&events).iter().all(|result| result.is_ok()));
+/// ```
+pub fn verify_events(events: &[SignedEvent]) -> Vec<Result<(), VerifyError>> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        events.par_iter().map(SignedEvent::verify).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        events.iter().map(SignedEvent::verify).collect()
+    }
+}
+
+/// Context shared by every signature verification instead of one per call
+fn verification_context() -> &'static Secp256k1<VerifyOnly> {
+    static CONTEXT: OnceLock<Secp256k1<VerifyOnly>> = OnceLock::new();
+    CONTEXT.get_or_init(Secp256k1::verification_only)
+}
+
+/// Serialize the fields of an event as the NIP-01 `[0, pubkey, created_at, kind, tags, content]` array
+fn commitment(
+    pubkey: &PublicKey,
+    created_at: i64,
+    kind: Kind,
+    tags: &[Vec<String>],
+    content: &str,
+) -> Result<String, Error> {
+    // Check if created_at is a valid Unix timestamp in seconds
+    if NaiveDateTime::from_timestamp_opt(created_at, 0).is_none() {
+        return Err(Error::InvalidEvent(
+            "created_at is not a valid timestamp".to_string(),
+        ));
+    }
+
+    Ok(json!([0, pubkey, created_at, kind, tags, content]).to_string())
+}
+
+pub fn get_event_hash(event: &UnsignedEvent) -> Result<EventId, Error> {
+    let commitment_string = serialize_event(event)?;
 
     let mut hasher = Sha256::new();
 
     hasher.update(commitment_string.as_bytes());
 
     let hash = hasher.finalize();
-    Ok(hex::encode(hash))
+    Ok(EventId::from_bytes(hash.into()))
 }
 
-pub fn serialize_event(evt: &UnsignedEvent) -> Result<String, String> {
-    if !validate_event(evt) {
-        return Err("Invalid event".to_string());
-    }
-    Ok(json!([
-        0,
-        evt.pubkey,
+pub fn serialize_event(evt: &UnsignedEvent) -> Result<String, Error> {
+    commitment(
+        &evt.pubkey,
         evt.created_at,
         evt.kind,
-        evt.tags,
-        evt.content
-    ])
-    .to_string())
+        &evt.tags,
+        &evt.content,
+    )
 }
 
-pub fn sign_event(event: &UnsignedEvent, key: &str) -> Result<SignedEvent, Error> {
-    let secp = Secp256k1::new();
-    let secret_key =
-        SecretKey::from_slice(&hex::decode(key).expect("FailedToDecodeHexPrivateKey"))?;
-    let pair = KeyPair::from_seckey_slice(&secp, &secret_key.secret_bytes())
-        .expect("Failed to generate keypair from secret key");
+/// Sign an event, fails if the pubkey of the event is not the public key of `keys`
+pub fn sign_event(event: &UnsignedEvent, keys: &Keys) -> Result<SignedEvent, Error> {
+    if event.pubkey != keys.public_key() {
+        return Err(Error::InvalidEvent(
+            "pubkey does not match the signing keys".to_string(),
+        ));
+    }
 
-    let message = Message::from_slice(
-        Sha256::digest(&serialize_event(event).unwrap().as_bytes()).as_slice(),
-    )?;
-    let sig = hex::encode(secp.sign_schnorr_no_aux_rand(&message, &pair).as_ref());
+    let secp = Secp256k1::signing_only();
 
-    let id = get_event_hash(event).unwrap();
+    let id = get_event_hash(event)?;
+    let message = Message::from_slice(id.as_bytes())?;
+    let mut key_pair = keys.key_pair();
+    let sig = secp.sign_schnorr_no_aux_rand(&message, &key_pair).into();
+    key_pair.non_secure_erase();
 
     Ok(SignedEvent {
         content: event.content.clone(),
         created_at: event.created_at,
         id,
         kind: event.kind,
-        pubkey: event.pubkey.clone(),
+        pubkey: event.pubkey,
         sig,
         tags: event.tags.clone(),
     })
@@ -83,29 +194,26 @@ pub fn validate_event(event: &UnsignedEvent) -> bool {
     }
 
     // Check if created_at is a valid Unix timestamp in seconds
-    let datetime_opt = NaiveDateTime::from_timestamp_opt(event.created_at as i64, 0);
+    let datetime_opt = NaiveDateTime::from_timestamp_opt(event.created_at, 0);
     if datetime_opt.is_none() {
         return false;
     }
 
-    if !matches!(event.pubkey, _ if event.pubkey.len() == 64 && event.pubkey.chars().all(|c| c.is_ascii_hexdigit()))
-    {
-        return false;
-    }
-
     true
 }
 
-pub fn verify_signature(signature: &str, pubkey: &str, id: &str) -> Result<(), Error> {
-    let secp = Secp256k1::new();
-
-    let public_key =
-        XOnlyPublicKey::from_slice(&hex::decode(pubkey).expect("FailedToDecodePubkey"))?;
-    let message =
-        Message::from_slice(&hex::decode(id).expect("UnableToDecodeHexMessageForSigning"))?;
-
-    let sig = Signature::from_slice(&hex::decode(signature).expect("FailedToDecodeSignature"))
-        .expect("FailedToParseSignature");
+pub fn verify_signature(
+    signature: &Signature,
+    pubkey: &PublicKey,
+    id: &EventId,
+) -> Result<(), Error> {
+    let message = Message::from_slice(id.as_bytes())?;
+
+    verification_context().verify_schnorr(
+        &signature.schnorr_signature(),
+        &message,
+        &pubkey.x_only_public_key(),
+    )?;
 
-    secp.verify_schnorr(&sig, &message, &public_key)
+    Ok(())
 }
@@ -1,34 +1,67 @@
 use rand::RngCore;
-use secp256k1::SecretKey;
+use zeroize::{Zeroize, Zeroizing};
 
-use super::utils::{bech32_encode, Prefix};
+use super::{error::Error, nip06, types::SecretKey};
 
 pub struct GeneratePrivateKey {
+    secret_key: SecretKey,
     hex_private_key: String,
-    bech32_private_key: String,
 }
 
 impl GeneratePrivateKey {
     pub fn new() -> Self {
         let mut rng = rand::thread_rng();
-        let mut bytes = [0u8; 32];
-        rng.fill_bytes(&mut bytes);
+        let mut bytes = Zeroizing::new([0u8; 32]);
 
-        let secret_key = SecretKey::from_slice(&bytes).expect("Error generating secret key");
+        // Almost every 32-byte value is a valid secret key, retry on the others
+        loop {
+            rng.fill_bytes(bytes.as_mut());
 
-        let hex_private_key = hex::encode(&secret_key[..]);
-        let bech32_private_key = bech32_encode(Prefix::Nsec, &hex_private_key);
+            if let Ok(secret_key) = SecretKey::from_slice(bytes.as_ref()) {
+                return Self::from_secret_key(secret_key);
+            }
+        }
+    }
+
+    /// Derive the key of an account from a BIP-39 mnemonic and an optional passphrase (NIP-06)
+    pub fn from_mnemonic(
+        mnemonic: &str,
+        passphrase: Option<&str>,
+        account: u32,
+    ) -> Result<Self, Error> {
+        Ok(Self::from_secret_key(nip06::derive_secret_key(
+            mnemonic, passphrase, account,
+        )?))
+    }
+
+    pub fn from_secret_key(secret_key: SecretKey) -> Self {
         Self {
-            hex_private_key,
-            bech32_private_key,
+            hex_private_key: secret_key.to_hex(),
+            secret_key,
         }
     }
 
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
     pub fn hex_private_key(&self) -> &str {
         &self.hex_private_key
     }
 
-    pub fn bech32_private_key(&self) -> &str {
-        &self.bech32_private_key
+    pub fn bech32_private_key(&self) -> Result<String, Error> {
+        self.secret_key.to_bech32()
+    }
+}
+
+impl Drop for GeneratePrivateKey {
+    fn drop(&mut self) {
+        self.hex_private_key.zeroize();
+    }
+}
+
+impl Default for GeneratePrivateKey {
+    fn default() -> Self {
+        Self::new()
     }
 }
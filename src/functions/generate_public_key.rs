@@ -1,34 +1,32 @@
-use secp256k1::{PublicKey, Secp256k1, SecretKey};
-
-use super::utils::{bech32_encode, Prefix};
+use super::{
+    error::Error,
+    types::{PublicKey, SecretKey},
+};
 
 pub struct GeneratePublicKey {
+    public_key: PublicKey,
     hex_public_key: String,
-    bech32_public_key: String,
 }
 
 impl GeneratePublicKey {
-    pub fn new(hex_private_key: &str) -> Self {
-        let secp = Secp256k1::new();
+    pub fn new(secret_key: &SecretKey) -> Self {
+        let public_key = secret_key.public_key();
 
-        let secret_key = SecretKey::from_slice(
-            &hex::decode(hex_private_key).expect("Error decoding hex private key"),
-        )
-        .expect("Error generating secret key");
-        let (pubkey, _) = PublicKey::from_secret_key(&secp, &secret_key).x_only_public_key();
-        let hex_public_key = hex::encode(&pubkey.serialize()[..]);
-        let bech32_public_key = bech32_encode(Prefix::Npub, &hex_public_key);
         Self {
-            hex_public_key,
-            bech32_public_key,
+            public_key,
+            hex_public_key: public_key.to_hex(),
         }
     }
 
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
     pub fn hex_public_key(&self) -> &str {
         &self.hex_public_key
     }
 
-    pub fn bech32_public_key(&self) -> &str {
-        &self.bech32_public_key
+    pub fn bech32_public_key(&self) -> Result<String, Error> {
+        self.public_key.to_bech32()
     }
 }
@@ -0,0 +1,119 @@
+use rand::RngCore;
+use secp256k1::{KeyPair, Secp256k1};
+use std::fmt;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use super::{
+    error::Error,
+    nip44,
+    types::{PublicKey, SecretKey},
+};
+
+/// Keys holds the keypair of a user, the secret key is erased from memory when it is dropped.
+///
+/// The erasure is best effort, see [`SecretKey`]: the key pairs returned by [`Keys::key_pair`]
+/// are not erased.
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::keys::Keys;
+///
+/// let keys = Keys::generate();
+/// let parsed = Keys::parse(&keys.secret_key().to_bech32().unwrap()).unwrap();
+///
+/// assert_eq!(keys.public_key(), parsed.public_key());
+/// assert!(!format!("{:?}", keys).contains(&keys.secret_key().to_hex()));
+/// ```
+#[derive(Clone)]
+pub struct Keys {
+    secret_key: SecretKey,
+    public_key: PublicKey,
+}
+
+impl Keys {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self {
+            public_key: secret_key.public_key(),
+            secret_key,
+        }
+    }
+
+    /// Generate new random keys
+    pub fn generate() -> Self {
+        let mut rng = rand::thread_rng();
+        let mut bytes = Zeroizing::new([0u8; 32]);
+
+        // Almost every 32-byte value is a valid secret key, retry on the others
+        loop {
+            rng.fill_bytes(bytes.as_mut());
+
+            if let Ok(keys) = Self::from_slice(bytes.as_ref()) {
+                return keys;
+            }
+        }
+    }
+
+    /// Create keys from the 32 bytes of a secret key
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self::new(SecretKey::from_slice(bytes)?))
+    }
+
+    pub fn from_hex(secret_key: &str) -> Result<Self, Error> {
+        Ok(Self::new(SecretKey::from_hex(secret_key)?))
+    }
+
+    pub fn from_nsec(secret_key: &str) -> Result<Self, Error> {
+        Ok(Self::new(SecretKey::from_bech32(secret_key)?))
+    }
+
+    /// Create keys from a hex or `nsec` bech32 secret key
+    pub fn parse(secret_key: &str) -> Result<Self, Error> {
+        Ok(Self::new(SecretKey::parse(secret_key)?))
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.public_key
+    }
+
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    /// Build the secp256k1 key pair, call `non_secure_erase` on it once done
+    pub fn key_pair(&self) -> KeyPair {
+        let secp = Secp256k1::signing_only();
+        let mut secret_key = self.secret_key.secp256k1_secret_key();
+        let key_pair = KeyPair::from_secret_key(&secp, &secret_key);
+        secret_key.non_secure_erase();
+
+        key_pair
+    }
+
+    /// Encrypt a message to `public_key` with NIP-44
+    pub fn nip44_encrypt(&self, public_key: &PublicKey, plaintext: &str) -> Result<String, Error> {
+        nip44::encrypt(&self.secret_key, public_key, plaintext)
+    }
+
+    /// Decrypt a NIP-44 payload received from `public_key`
+    pub fn nip44_decrypt(&self, public_key: &PublicKey, payload: &str) -> Result<String, Error> {
+        nip44::decrypt(&self.secret_key, public_key, payload)
+    }
+}
+
+impl fmt::Debug for Keys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keys")
+            .field("public_key", &self.public_key.to_hex())
+            .field("secret_key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl PartialEq for Keys {
+    fn eq(&self, other: &Self) -> bool {
+        self.secret_key == other.secret_key
+    }
+}
+
+impl Eq for Keys {}
+
+impl ZeroizeOnDrop for Keys {}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Kind of an event, serialized as its number
+///
+/// Kinds are compared, hashed and sorted by number, `Kind::Custom(1)` is equal to `Kind::TextNote`.
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::kind::Kind;
+///
+/// assert_eq!(Kind::from(1), Kind::TextNote);
+/// assert_eq!(Kind::from(30078), Kind::Custom(30078));
+/// assert!(Kind::Custom(30078).is_addressable());
+/// assert_eq!(Kind::Custom(1), Kind::TextNote);
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "u16", into = "u16")]
+pub enum Kind {
+    /// NIP-01
+    Metadata,
+    /// NIP-01
+    TextNote,
+    /// NIP-01 (deprecated)
+    RecommendRelay,
+    /// NIP-02
+    ContactList,
+    /// NIP-04
+    EncryptedDirectMessage,
+    /// NIP-09
+    EventDeletion,
+    /// NIP-18
+    Repost,
+    /// NIP-25
+    Reaction,
+    /// NIP-58
+    BadgeAward,
+    /// NIP-59
+    Seal,
+    /// NIP-17
+    PrivateDirectMessage,
+    /// NIP-18
+    GenericRepost,
+    /// NIP-28
+    ChannelCreation,
+    /// NIP-28
+    ChannelMetadata,
+    /// NIP-28
+    ChannelMessage,
+    /// NIP-59
+    GiftWrap,
+    /// NIP-94
+    FileMetadata,
+    /// NIP-56
+    Reporting,
+    /// NIP-32
+    Label,
+    /// NIP-57
+    ZapRequest,
+    /// NIP-57
+    Zap,
+    /// NIP-51
+    MuteList,
+    /// NIP-51
+    PinList,
+    /// NIP-65
+    RelayList,
+    /// NIP-17
+    InboxRelays,
+    /// NIP-42
+    Authentication,
+    /// NIP-46
+    NostrConnect,
+    /// NIP-98
+    HttpAuth,
+    /// NIP-23
+    LongFormTextNote,
+    /// Any kind without a named variant
+    Custom(u16),
+}
+
+impl Kind {
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            Kind::Metadata => 0,
+            Kind::TextNote => 1,
+            Kind::RecommendRelay => 2,
+            Kind::ContactList => 3,
+            Kind::EncryptedDirectMessage => 4,
+            Kind::EventDeletion => 5,
+            Kind::Repost => 6,
+            Kind::Reaction => 7,
+            Kind::BadgeAward => 8,
+            Kind::Seal => 13,
+            Kind::PrivateDirectMessage => 14,
+            Kind::GenericRepost => 16,
+            Kind::ChannelCreation => 40,
+            Kind::ChannelMetadata => 41,
+            Kind::ChannelMessage => 42,
+            Kind::GiftWrap => 1059,
+            Kind::FileMetadata => 1063,
+            Kind::Reporting => 1984,
+            Kind::Label => 1985,
+            Kind::ZapRequest => 9734,
+            Kind::Zap => 9735,
+            Kind::MuteList => 10000,
+            Kind::PinList => 10001,
+            Kind::RelayList => 10002,
+            Kind::InboxRelays => 10050,
+            Kind::Authentication => 22242,
+            Kind::NostrConnect => 24133,
+            Kind::HttpAuth => 27235,
+            Kind::LongFormTextNote => 30023,
+            Kind::Custom(kind) => *kind,
+        }
+    }
+
+    /// Regular events are all stored by relays
+    pub fn is_regular(&self) -> bool {
+        let kind = self.as_u16();
+        (1000..10000).contains(&kind) || (4..45).contains(&kind) || kind == 1 || kind == 2
+    }
+
+    /// Only the latest replaceable event of a pubkey and kind is stored by relays
+    pub fn is_replaceable(&self) -> bool {
+        let kind = self.as_u16();
+        (10000..20000).contains(&kind) || kind == 0 || kind == 3
+    }
+
+    /// Ephemeral events are not stored by relays
+    pub fn is_ephemeral(&self) -> bool {
+        (20000..30000).contains(&self.as_u16())
+    }
+
+    /// Only the latest addressable event of a pubkey, kind and "d" tag is stored by relays
+    pub fn is_addressable(&self) -> bool {
+        (30000..40000).contains(&self.as_u16())
+    }
+}
+
+impl PartialEq for Kind {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_u16() == other.as_u16()
+    }
+}
+
+impl Eq for Kind {}
+
+impl Hash for Kind {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_u16().hash(state);
+    }
+}
+
+impl PartialOrd for Kind {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Kind {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_u16().cmp(&other.as_u16())
+    }
+}
+
+impl From<u16> for Kind {
+    fn from(kind: u16) -> Self {
+        match kind {
+            0 => Kind::Metadata,
+            1 => Kind::TextNote,
+            2 => Kind::RecommendRelay,
+            3 => Kind::ContactList,
+            4 => Kind::EncryptedDirectMessage,
+            5 => Kind::EventDeletion,
+            6 => Kind::Repost,
+            7 => Kind::Reaction,
+            8 => Kind::BadgeAward,
+            13 => Kind::Seal,
+            14 => Kind::PrivateDirectMessage,
+            16 => Kind::GenericRepost,
+            40 => Kind::ChannelCreation,
+            41 => Kind::ChannelMetadata,
+            42 => Kind::ChannelMessage,
+            1059 => Kind::GiftWrap,
+            1063 => Kind::FileMetadata,
+            1984 => Kind::Reporting,
+            1985 => Kind::Label,
+            9734 => Kind::ZapRequest,
+            9735 => Kind::Zap,
+            10000 => Kind::MuteList,
+            10001 => Kind::PinList,
+            10002 => Kind::RelayList,
+            10050 => Kind::InboxRelays,
+            22242 => Kind::Authentication,
+            24133 => Kind::NostrConnect,
+            27235 => Kind::HttpAuth,
+            30023 => Kind::LongFormTextNote,
+            kind => Kind::Custom(kind),
+        }
+    }
+}
+
+impl From<Kind> for u16 {
+    fn from(kind: Kind) -> Self {
+        kind.as_u16()
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_u16())
+    }
+}
@@ -1,7 +1,20 @@
 pub mod client;
 pub mod convert_key;
+pub mod error;
+pub mod event_builder;
 pub mod event_methods;
 pub mod generate_private_key;
 pub mod generate_public_key;
+pub mod keys;
+pub mod kind;
+pub mod nip04;
 pub mod nip05_query;
+pub mod nip06;
+pub mod nip19;
+pub mod nip21;
+pub mod nip44;
+pub mod nip59;
+pub mod relay_pool;
+pub mod signer;
+pub mod types;
 pub mod utils;
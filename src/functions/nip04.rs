@@ -0,0 +1,81 @@
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::Rng;
+use thiserror::Error;
+
+use super::{
+    error::Error,
+    types::{PublicKey, SecretKey},
+    utils::shared_secret,
+};
+
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Nip04Error {
+    #[error("Content is not in the <ciphertext>?iv=<iv> format")]
+    InvalidContent,
+
+    #[error("Invalid base64 in content")]
+    InvalidBase64,
+
+    #[error("Decryption failed")]
+    Decryption,
+}
+
+/// Encrypt a message from `secret_key` to `public_key`, returns `<ciphertext>?iv=<iv>` in base64
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{keys::Keys, nip04};
+///
+/// let alice = Keys::generate();
+/// let bob = Keys::generate();
+///
+/// let content = nip04::encrypt(alice.secret_key(), &bob.public_key(), "hello").unwrap();
+/// let message = nip04::decrypt(bob.secret_key(), &alice.public_key(), &content).unwrap();
+/// assert_eq!(message, "hello");
+/// ```
+pub fn encrypt(
+    secret_key: &SecretKey,
+    public_key: &PublicKey,
+    plaintext: &str,
+) -> Result<String, Error> {
+    let key = shared_secret(secret_key, public_key);
+    let iv: [u8; 16] = rand::thread_rng().gen();
+
+    let ciphertext = Aes256CbcEnc::new(key.as_ref().into(), &iv.into())
+        .encrypt_padded_vec_mut::<Pkcs7>(plaintext.as_bytes());
+
+    Ok(format!(
+        "{}?iv={}",
+        STANDARD.encode(ciphertext),
+        STANDARD.encode(iv)
+    ))
+}
+
+/// Decrypt a `<ciphertext>?iv=<iv>` message exchanged between `secret_key` and `public_key`
+pub fn decrypt(
+    secret_key: &SecretKey,
+    public_key: &PublicKey,
+    content: &str,
+) -> Result<String, Error> {
+    let (ciphertext, iv) = content
+        .split_once("?iv=")
+        .ok_or(Nip04Error::InvalidContent)?;
+    let ciphertext = STANDARD
+        .decode(ciphertext)
+        .map_err(|_| Nip04Error::InvalidBase64)?;
+    let iv: [u8; 16] = STANDARD
+        .decode(iv)
+        .map_err(|_| Nip04Error::InvalidBase64)?
+        .try_into()
+        .map_err(|_| Nip04Error::InvalidContent)?;
+
+    let key = shared_secret(secret_key, public_key);
+    let plaintext = Aes256CbcDec::new(key.as_ref().into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(&ciphertext)
+        .map_err(|_| Nip04Error::Decryption)?;
+
+    String::from_utf8(plaintext).map_err(|_| Nip04Error::Decryption.into())
+}
@@ -0,0 +1,119 @@
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use secp256k1::{Scalar, Secp256k1};
+use sha2::Sha512;
+use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{error::Error, types::SecretKey};
+
+/// `m/44'/1237'`, the account is appended as the next hardened index
+const PURPOSE: u32 = 44;
+const COIN_TYPE: u32 = 1237;
+const HARDENED: u32 = 1 << 31;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Nip06Error {
+    #[error("Invalid mnemonic: {0}")]
+    Mnemonic(#[from] bip39::Error),
+
+    #[error("Account {0} is not below 2^31")]
+    InvalidAccount(u32),
+}
+
+/// Generate a new english BIP-39 mnemonic of 12, 15, 18, 21 or 24 words
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{nip06, GeneratePrivateKey};
+///
+/// let mnemonic = nip06::generate_mnemonic(12).unwrap();
+/// let key = GeneratePrivateKey::from_mnemonic(&mnemonic, None, 0).unwrap();
+///
+/// assert_eq!(mnemonic.split(' ').count(), 12);
+/// assert_eq!(
+///     key.secret_key(),
+///     GeneratePrivateKey::from_mnemonic(&mnemonic, None, 0).unwrap().secret_key()
+/// );
+/// ```
+pub fn generate_mnemonic(word_count: usize) -> Result<String, Error> {
+    Ok(Mnemonic::generate(word_count)
+        .map_err(Nip06Error::from)?
+        .to_string())
+}
+
+/// Derive the secret key at `m/44'/1237'/<account>'/0/0` from a BIP-39 mnemonic and an
+/// optional passphrase
+pub fn derive_secret_key(
+    mnemonic: &str,
+    passphrase: Option<&str>,
+    account: u32,
+) -> Result<SecretKey, Error> {
+    if account >= HARDENED {
+        return Err(Nip06Error::InvalidAccount(account).into());
+    }
+
+    let mnemonic = Mnemonic::parse(mnemonic).map_err(Nip06Error::from)?;
+    let seed = Zeroizing::new(mnemonic.to_seed(passphrase.unwrap_or_default()));
+
+    let (master_key, mut chain_code) = hmac_sha512(b"Bitcoin seed", &[seed.as_ref()]);
+    let mut secret_key = SecretKey::from_slice(master_key.as_ref())?;
+
+    for index in [
+        PURPOSE | HARDENED,
+        COIN_TYPE | HARDENED,
+        account | HARDENED,
+        0,
+        0,
+    ] {
+        (secret_key, chain_code) = derive_child(&secret_key, &chain_code, index)?;
+    }
+
+    Ok(secret_key)
+}
+
+fn hmac_sha512(key: &[u8], data: &[&[u8]]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    data.iter().for_each(|data| mac.update(data));
+    let mut output = mac.finalize().into_bytes();
+
+    let mut left = Zeroizing::new([0u8; 32]);
+    let mut right = Zeroizing::new([0u8; 32]);
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    output.zeroize();
+    (left, right)
+}
+
+/// BIP-32 private child key derivation, the secp256k1 values built along the way are erased
+fn derive_child(
+    secret_key: &SecretKey,
+    chain_code: &[u8; 32],
+    index: u32,
+) -> Result<(SecretKey, Zeroizing<[u8; 32]>), Error> {
+    let mut parent = secret_key.secp256k1_secret_key();
+    let (tweak, chain_code) = if index >= HARDENED {
+        hmac_sha512(
+            chain_code,
+            &[&[0], secret_key.secret_bytes(), &index.to_be_bytes()],
+        )
+    } else {
+        let public_key = secp256k1::PublicKey::from_secret_key(&Secp256k1::signing_only(), &parent);
+        hmac_sha512(chain_code, &[&public_key.serialize(), &index.to_be_bytes()])
+    };
+
+    // Tweaks out of the curve order have a probability below 2^-127
+    let child = Scalar::from_be_bytes(*tweak)
+        .map_err(|_| secp256k1::Error::InvalidTweak)
+        .and_then(|mut tweak| {
+            let child = parent.add_tweak(&tweak);
+            tweak.non_secure_erase();
+            child
+        });
+    parent.non_secure_erase();
+
+    let mut child = child?;
+    let secret_key = SecretKey::from(child);
+    child.non_secure_erase();
+
+    Ok((secret_key, chain_code))
+}
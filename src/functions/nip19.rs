@@ -0,0 +1,386 @@
+use thiserror::Error;
+
+use super::{
+    error::Error,
+    kind::Kind,
+    types::{decode_bech32, EventId, PublicKey, SecretKey},
+    utils::{bech32_decode, bech32_encode, Prefix},
+};
+
+/// TLV types of NIP-19, unknown types are ignored when decoding
+const SPECIAL: u8 = 0;
+const RELAY: u8 = 1;
+const AUTHOR: u8 = 2;
+const KIND: u8 = 3;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Nip19Error {
+    #[error("TLV entry is truncated")]
+    TruncatedTlv,
+
+    #[error("Missing {0} in TLV entity")]
+    MissingField(&'static str),
+
+    #[error("Invalid {0} in TLV entity")]
+    InvalidField(&'static str),
+
+    #[error("Unknown NIP-19 prefix {0}")]
+    UnknownPrefix(String),
+}
+
+/// Append a TLV entry, values are limited to 255 bytes
+fn write_tlv(
+    buffer: &mut Vec<u8>,
+    tlv_type: u8,
+    value: &[u8],
+    field: &'static str,
+) -> Result<(), Nip19Error> {
+    let length = u8::try_from(value.len()).map_err(|_| Nip19Error::InvalidField(field))?;
+
+    buffer.push(tlv_type);
+    buffer.push(length);
+    buffer.extend_from_slice(value);
+
+    Ok(())
+}
+
+/// Split TLV data into its `(type, value)` entries
+fn read_tlv(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>, Nip19Error> {
+    let mut entries = Vec::new();
+
+    while !data.is_empty() {
+        let [tlv_type, length, rest @ ..] = data else {
+            return Err(Nip19Error::TruncatedTlv);
+        };
+        let length = *length as usize;
+
+        if rest.len() < length {
+            return Err(Nip19Error::TruncatedTlv);
+        }
+
+        entries.push((*tlv_type, &rest[..length]));
+        data = &rest[length..];
+    }
+
+    Ok(entries)
+}
+
+fn write_relays(buffer: &mut Vec<u8>, relays: &[String]) -> Result<(), Nip19Error> {
+    relays
+        .iter()
+        .try_for_each(|relay| write_tlv(buffer, RELAY, relay.as_bytes(), "relay"))
+}
+
+fn read_string(value: &[u8], field: &'static str) -> Result<String, Nip19Error> {
+    String::from_utf8(value.to_vec()).map_err(|_| Nip19Error::InvalidField(field))
+}
+
+fn read_public_key(value: &[u8]) -> Result<PublicKey, Nip19Error> {
+    PublicKey::from_slice(value).map_err(|_| Nip19Error::InvalidField("author"))
+}
+
+/// Kinds are encoded as 32-bit big-endian integers
+fn read_kind(value: &[u8]) -> Result<Kind, Nip19Error> {
+    let bytes: [u8; 4] = value
+        .try_into()
+        .map_err(|_| Nip19Error::InvalidField("kind"))?;
+    let kind =
+        u16::try_from(u32::from_be_bytes(bytes)).map_err(|_| Nip19Error::InvalidField("kind"))?;
+
+    Ok(kind.into())
+}
+
+fn kind_bytes(kind: Kind) -> [u8; 4] {
+    u32::from(kind.as_u16()).to_be_bytes()
+}
+
+/// `nprofile` entity, a public key with relay hints
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{keys::Keys, nip19::Nip19Profile};
+///
+/// let profile = Nip19Profile {
+///     public_key: Keys::generate().public_key(),
+///     relays: vec!["wss://relay.damus.io".to_string()],
+/// };
+/// let nprofile = profile.to_bech32().unwrap();
+///
+/// assert!(nprofile.starts_with("nprofile1"));
+/// assert_eq!(Nip19Profile::from_bech32(&nprofile).unwrap(), profile);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nip19Profile {
+    pub public_key: PublicKey,
+    pub relays: Vec<String>,
+}
+
+impl Nip19Profile {
+    pub fn new(public_key: PublicKey) -> Self {
+        Self {
+            public_key,
+            relays: Vec::new(),
+        }
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        let mut data = Vec::new();
+        write_tlv(&mut data, SPECIAL, &self.public_key.to_bytes(), "pubkey")?;
+        write_relays(&mut data, &self.relays)?;
+
+        bech32_encode(Prefix::Nprofile, &data)
+    }
+
+    pub fn from_bech32(value: &str) -> Result<Self, Error> {
+        Self::from_tlv(&decode_bech32(value, Prefix::Nprofile)?)
+    }
+
+    pub(crate) fn from_tlv(data: &[u8]) -> Result<Self, Error> {
+        let mut public_key = None;
+        let mut relays = Vec::new();
+
+        for (tlv_type, value) in read_tlv(data)? {
+            match tlv_type {
+                SPECIAL => {
+                    public_key = Some(
+                        PublicKey::from_slice(value)
+                            .map_err(|_| Nip19Error::InvalidField("pubkey"))?,
+                    )
+                }
+                RELAY => relays.push(read_string(value, "relay")?),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            public_key: public_key.ok_or(Nip19Error::MissingField("pubkey"))?,
+            relays,
+        })
+    }
+}
+
+/// `nevent` entity, an event id with optional relays, author and kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nip19Event {
+    pub event_id: EventId,
+    pub author: Option<PublicKey>,
+    pub kind: Option<Kind>,
+    pub relays: Vec<String>,
+}
+
+impl Nip19Event {
+    pub fn new(event_id: EventId) -> Self {
+        Self {
+            event_id,
+            author: None,
+            kind: None,
+            relays: Vec::new(),
+        }
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        let mut data = Vec::new();
+        write_tlv(&mut data, SPECIAL, self.event_id.as_bytes(), "event id")?;
+        write_relays(&mut data, &self.relays)?;
+
+        if let Some(author) = &self.author {
+            write_tlv(&mut data, AUTHOR, &author.to_bytes(), "author")?;
+        }
+
+        if let Some(kind) = self.kind {
+            write_tlv(&mut data, KIND, &kind_bytes(kind), "kind")?;
+        }
+
+        bech32_encode(Prefix::Nevent, &data)
+    }
+
+    pub fn from_bech32(value: &str) -> Result<Self, Error> {
+        Self::from_tlv(&decode_bech32(value, Prefix::Nevent)?)
+    }
+
+    pub(crate) fn from_tlv(data: &[u8]) -> Result<Self, Error> {
+        let mut event_id = None;
+        let mut author = None;
+        let mut kind = None;
+        let mut relays = Vec::new();
+
+        for (tlv_type, value) in read_tlv(data)? {
+            match tlv_type {
+                SPECIAL => {
+                    event_id = Some(
+                        EventId::from_slice(value)
+                            .map_err(|_| Nip19Error::InvalidField("event id"))?,
+                    )
+                }
+                RELAY => relays.push(read_string(value, "relay")?),
+                AUTHOR => author = Some(read_public_key(value)?),
+                KIND => kind = Some(read_kind(value)?),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            event_id: event_id.ok_or(Nip19Error::MissingField("event id"))?,
+            author,
+            kind,
+            relays,
+        })
+    }
+}
+
+/// `naddr` entity, the coordinate of an addressable or replaceable event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nip19Address {
+    pub identifier: String,
+    pub public_key: PublicKey,
+    pub kind: Kind,
+    pub relays: Vec<String>,
+}
+
+impl Nip19Address {
+    pub fn new(kind: Kind, public_key: PublicKey, identifier: &str) -> Self {
+        Self {
+            identifier: identifier.to_string(),
+            public_key,
+            kind,
+            relays: Vec::new(),
+        }
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        let mut data = Vec::new();
+        write_tlv(&mut data, SPECIAL, self.identifier.as_bytes(), "identifier")?;
+        write_relays(&mut data, &self.relays)?;
+        write_tlv(&mut data, AUTHOR, &self.public_key.to_bytes(), "author")?;
+        write_tlv(&mut data, KIND, &kind_bytes(self.kind), "kind")?;
+
+        bech32_encode(Prefix::Naddr, &data)
+    }
+
+    pub fn from_bech32(value: &str) -> Result<Self, Error> {
+        Self::from_tlv(&decode_bech32(value, Prefix::Naddr)?)
+    }
+
+    pub(crate) fn from_tlv(data: &[u8]) -> Result<Self, Error> {
+        let mut identifier = None;
+        let mut public_key = None;
+        let mut kind = None;
+        let mut relays = Vec::new();
+
+        for (tlv_type, value) in read_tlv(data)? {
+            match tlv_type {
+                SPECIAL => identifier = Some(read_string(value, "identifier")?),
+                RELAY => relays.push(read_string(value, "relay")?),
+                AUTHOR => public_key = Some(read_public_key(value)?),
+                KIND => kind = Some(read_kind(value)?),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            identifier: identifier.ok_or(Nip19Error::MissingField("identifier"))?,
+            public_key: public_key.ok_or(Nip19Error::MissingField("author"))?,
+            kind: kind.ok_or(Nip19Error::MissingField("kind"))?,
+            relays,
+        })
+    }
+}
+
+/// `nrelay` entity, deprecated by NIP-19 but still found in the wild
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Nip19Relay {
+    pub url: String,
+}
+
+impl Nip19Relay {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+        }
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        let mut data = Vec::new();
+        write_tlv(&mut data, SPECIAL, self.url.as_bytes(), "relay")?;
+
+        bech32_encode(Prefix::Nrelay, &data)
+    }
+
+    pub fn from_bech32(value: &str) -> Result<Self, Error> {
+        Self::from_tlv(&decode_bech32(value, Prefix::Nrelay)?)
+    }
+
+    pub(crate) fn from_tlv(data: &[u8]) -> Result<Self, Error> {
+        let url = read_tlv(data)?
+            .into_iter()
+            .find(|(tlv_type, _)| *tlv_type == SPECIAL)
+            .ok_or(Nip19Error::MissingField("relay"))?
+            .1;
+
+        Ok(Self {
+            url: read_string(url, "relay")?,
+        })
+    }
+}
+
+/// Any NIP-19 entity, decoded according to its prefix
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{keys::Keys, nip19::Nip19};
+///
+/// let keys = Keys::generate();
+/// let npub = keys.public_key().to_bech32().unwrap();
+///
+/// assert_eq!(Nip19::from_bech32(&npub).unwrap(), Nip19::Npub(keys.public_key()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Nip19 {
+    Npub(PublicKey),
+    Nsec(SecretKey),
+    Note(EventId),
+    Profile(Nip19Profile),
+    Event(Nip19Event),
+    Address(Nip19Address),
+    Relay(Nip19Relay),
+}
+
+impl Nip19 {
+    pub fn from_bech32(value: &str) -> Result<Self, Error> {
+        let (hrp, data) = bech32_decode(value)?;
+
+        Ok(match hrp.as_str() {
+            "npub" => Self::Npub(PublicKey::from_slice(&data)?),
+            "nsec" => Self::Nsec(SecretKey::from_slice(&data)?),
+            "note" => Self::Note(EventId::from_slice(&data)?),
+            "nprofile" => Self::Profile(Nip19Profile::from_tlv(&data)?),
+            "nevent" => Self::Event(Nip19Event::from_tlv(&data)?),
+            "naddr" => Self::Address(Nip19Address::from_tlv(&data)?),
+            "nrelay" => Self::Relay(Nip19Relay::from_tlv(&data)?),
+            _ => return Err(Nip19Error::UnknownPrefix(hrp).into()),
+        })
+    }
+
+    /// Human readable prefix of the entity
+    pub fn hrp(&self) -> &'static str {
+        match self {
+            Self::Npub(_) => "npub",
+            Self::Nsec(_) => "nsec",
+            Self::Note(_) => "note",
+            Self::Profile(_) => "nprofile",
+            Self::Event(_) => "nevent",
+            Self::Address(_) => "naddr",
+            Self::Relay(_) => "nrelay",
+        }
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        match self {
+            Self::Npub(public_key) => public_key.to_bech32(),
+            Self::Nsec(secret_key) => secret_key.to_bech32(),
+            Self::Note(event_id) => event_id.to_bech32(),
+            Self::Profile(profile) => profile.to_bech32(),
+            Self::Event(event) => event.to_bech32(),
+            Self::Address(address) => address.to_bech32(),
+            Self::Relay(relay) => relay.to_bech32(),
+        }
+    }
+}
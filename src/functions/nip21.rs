@@ -0,0 +1,78 @@
+use std::str::FromStr;
+use thiserror::Error;
+
+use super::{error::Error, nip19::Nip19};
+
+const SCHEME: &str = "nostr:";
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Nip21Error {
+    #[error("URI does not start with nostr:")]
+    MissingScheme,
+
+    #[error("nostr: URIs can't contain a secret key")]
+    SecretKey,
+}
+
+/// NIP-21 `nostr:` URI of any NIP-19 entity except `nsec`
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{keys::Keys, nip19::Nip19, nip21::NostrUri};
+///
+/// let public_key = Keys::generate().public_key();
+/// let uri = NostrUri::new(Nip19::Npub(public_key)).unwrap().to_uri().unwrap();
+///
+/// assert!(uri.starts_with("nostr:npub1"));
+/// assert_eq!(NostrUri::parse(&uri).unwrap().entity(), &Nip19::Npub(public_key));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NostrUri(Nip19);
+
+impl NostrUri {
+    pub fn new(entity: Nip19) -> Result<Self, Error> {
+        if matches!(entity, Nip19::Nsec(_)) {
+            return Err(Nip21Error::SecretKey.into());
+        }
+
+        Ok(Self(entity))
+    }
+
+    /// Parse a `nostr:` URI, the scheme is case insensitive
+    pub fn parse(uri: &str) -> Result<Self, Error> {
+        let entity = uri
+            .get(..SCHEME.len())
+            .filter(|scheme| scheme.eq_ignore_ascii_case(SCHEME))
+            .map(|_| &uri[SCHEME.len()..])
+            .ok_or(Nip21Error::MissingScheme)?;
+
+        Self::new(Nip19::from_bech32(entity)?)
+    }
+
+    pub fn entity(&self) -> &Nip19 {
+        &self.0
+    }
+
+    pub fn into_entity(self) -> Nip19 {
+        self.0
+    }
+
+    pub fn to_uri(&self) -> Result<String, Error> {
+        Ok(format!("{SCHEME}{}", self.0.to_bech32()?))
+    }
+}
+
+impl FromStr for NostrUri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        Self::parse(uri)
+    }
+}
+
+impl TryFrom<Nip19> for NostrUri {
+    type Error = Error;
+
+    fn try_from(entity: Nip19) -> Result<Self, Self::Error> {
+        Self::new(entity)
+    }
+}
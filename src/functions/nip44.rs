@@ -0,0 +1,275 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher},
+    ChaCha20,
+};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use sha2::Sha256;
+use std::fmt;
+use thiserror::Error;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use super::{
+    error::Error,
+    types::{PublicKey, SecretKey},
+    utils::shared_secret,
+};
+
+const VERSION: u8 = 2;
+const SALT: &[u8] = b"nip44-v2";
+const MIN_PLAINTEXT_LEN: usize = 1;
+const MAX_PLAINTEXT_LEN: usize = 65535;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Nip44Error {
+    #[error("Plaintext must be between 1 and 65535 bytes, found {0}")]
+    InvalidMessageLength(usize),
+
+    #[error("Invalid payload")]
+    InvalidPayload,
+
+    #[error("Unknown encryption version {0}")]
+    UnknownVersion(u8),
+
+    #[error("Invalid MAC")]
+    InvalidMac,
+
+    #[error("Invalid padding")]
+    InvalidPadding,
+}
+
+/// Key shared by two users, derived from their ECDH secret with HKDF, erased when dropped
+#[derive(Clone, PartialEq, Eq)]
+pub struct ConversationKey(Zeroizing<[u8; 32]>);
+
+impl ConversationKey {
+    pub fn derive(secret_key: &SecretKey, public_key: &PublicKey) -> Self {
+        let (key, _) =
+            Hkdf::<Sha256>::extract(Some(SALT), shared_secret(secret_key, public_key).as_ref());
+
+        Self(Zeroizing::new(key.into()))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ConversationKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConversationKey(<redacted>)")
+    }
+}
+
+impl ZeroizeOnDrop for ConversationKey {}
+
+/// ChaCha20 key, ChaCha20 nonce and HMAC key of a message, erased when dropped
+#[derive(Clone, PartialEq, Eq)]
+pub struct MessageKeys {
+    chacha_key: Zeroizing<[u8; 32]>,
+    chacha_nonce: Zeroizing<[u8; 12]>,
+    hmac_key: Zeroizing<[u8; 32]>,
+}
+
+impl MessageKeys {
+    pub fn chacha_key(&self) -> &[u8; 32] {
+        &self.chacha_key
+    }
+
+    pub fn chacha_nonce(&self) -> &[u8; 12] {
+        &self.chacha_nonce
+    }
+
+    pub fn hmac_key(&self) -> &[u8; 32] {
+        &self.hmac_key
+    }
+
+    fn apply_keystream(&self, buffer: &mut [u8]) {
+        ChaCha20::new(
+            self.chacha_key.as_ref().into(),
+            self.chacha_nonce.as_ref().into(),
+        )
+        .apply_keystream(buffer);
+    }
+
+    fn mac(&self, nonce: &[u8; 32], ciphertext: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.hmac_key.as_ref())
+            .expect("HMAC accepts keys of any length");
+        mac.update(nonce);
+        mac.update(ciphertext);
+        mac
+    }
+}
+
+impl fmt::Debug for MessageKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MessageKeys(<redacted>)")
+    }
+}
+
+impl ZeroizeOnDrop for MessageKeys {}
+
+/// Expand the conversation key and a message nonce into the keys of that message
+pub fn get_message_keys(conversation_key: &ConversationKey, nonce: &[u8; 32]) -> MessageKeys {
+    let hkdf =
+        Hkdf::<Sha256>::from_prk(conversation_key.as_bytes()).expect("32 bytes is a valid PRK");
+    let mut keys = Zeroizing::new([0u8; 76]);
+    hkdf.expand(nonce, keys.as_mut())
+        .expect("76 bytes is a valid HKDF output length");
+
+    let mut message_keys = MessageKeys {
+        chacha_key: Zeroizing::new([0; 32]),
+        chacha_nonce: Zeroizing::new([0; 12]),
+        hmac_key: Zeroizing::new([0; 32]),
+    };
+    message_keys.chacha_key.copy_from_slice(&keys[..32]);
+    message_keys.chacha_nonce.copy_from_slice(&keys[32..44]);
+    message_keys.hmac_key.copy_from_slice(&keys[44..]);
+    message_keys
+}
+
+/// Length of a padded plaintext, without the 2-byte length prefix
+pub fn calc_padded_len(len: usize) -> usize {
+    if len <= 32 {
+        return 32;
+    }
+
+    let next_power = 1 << (usize::BITS - (len - 1).leading_zeros());
+    let chunk = if next_power <= 256 {
+        32
+    } else {
+        next_power / 8
+    };
+
+    chunk * ((len - 1) / chunk + 1)
+}
+
+fn pad(plaintext: &str) -> Result<Vec<u8>, Nip44Error> {
+    let len = plaintext.len();
+    if !(MIN_PLAINTEXT_LEN..=MAX_PLAINTEXT_LEN).contains(&len) {
+        return Err(Nip44Error::InvalidMessageLength(len));
+    }
+
+    let mut padded = Vec::with_capacity(2 + calc_padded_len(len));
+    padded.extend_from_slice(&(len as u16).to_be_bytes());
+    padded.extend_from_slice(plaintext.as_bytes());
+    padded.resize(2 + calc_padded_len(len), 0);
+
+    Ok(padded)
+}
+
+fn unpad(padded: &[u8]) -> Result<String, Nip44Error> {
+    let [high, low, plaintext @ ..] = padded else {
+        return Err(Nip44Error::InvalidPadding);
+    };
+    let len = u16::from_be_bytes([*high, *low]) as usize;
+
+    if len < MIN_PLAINTEXT_LEN || plaintext.len() != calc_padded_len(len) {
+        return Err(Nip44Error::InvalidPadding);
+    }
+
+    String::from_utf8(plaintext[..len].to_vec()).map_err(|_| Nip44Error::InvalidPadding)
+}
+
+/// Encrypt a message with a conversation key and the given 32-byte nonce, the nonce must never
+/// be reused. Use [`encrypt`] unless the nonce has to be chosen, e.g. for test vectors.
+pub fn encrypt_with_conversation_key(
+    conversation_key: &ConversationKey,
+    plaintext: &str,
+    nonce: &[u8; 32],
+) -> Result<String, Error> {
+    let keys = get_message_keys(conversation_key, nonce);
+
+    let mut ciphertext = pad(plaintext)?;
+    keys.apply_keystream(&mut ciphertext);
+    let mac = keys.mac(nonce, &ciphertext).finalize().into_bytes();
+
+    let mut payload = Vec::with_capacity(1 + 32 + ciphertext.len() + 32);
+    payload.push(VERSION);
+    payload.extend_from_slice(nonce);
+    payload.extend_from_slice(&ciphertext);
+    payload.extend_from_slice(&mac);
+
+    Ok(STANDARD.encode(payload))
+}
+
+/// Decrypt a base64 payload with a conversation key
+pub fn decrypt_with_conversation_key(
+    conversation_key: &ConversationKey,
+    payload: &str,
+) -> Result<String, Error> {
+    // Non-base64 payloads starting with # are reserved for future versions
+    if payload.starts_with('#') {
+        return Err(Nip44Error::UnknownVersion(0).into());
+    }
+
+    if !(132..=87472).contains(&payload.len()) {
+        return Err(Nip44Error::InvalidPayload.into());
+    }
+
+    let data = STANDARD
+        .decode(payload)
+        .map_err(|_| Nip44Error::InvalidPayload)?;
+
+    if !(99..=65603).contains(&data.len()) {
+        return Err(Nip44Error::InvalidPayload.into());
+    }
+
+    if data[0] != VERSION {
+        return Err(Nip44Error::UnknownVersion(data[0]).into());
+    }
+
+    let nonce: &[u8; 32] = data[1..33].try_into().expect("slice of 32 bytes");
+    let (ciphertext, mac) = data[33..].split_at(data.len() - 33 - 32);
+
+    let keys = get_message_keys(conversation_key, nonce);
+    keys.mac(nonce, ciphertext)
+        .verify_slice(mac)
+        .map_err(|_| Nip44Error::InvalidMac)?;
+
+    let mut padded = ciphertext.to_vec();
+    keys.apply_keystream(&mut padded);
+
+    Ok(unpad(&padded)?)
+}
+
+/// Encrypt a message from `secret_key` to `public_key` with a random nonce
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{keys::Keys, nip44};
+///
+/// let alice = Keys::generate();
+/// let bob = Keys::generate();
+///
+/// let payload = nip44::encrypt(alice.secret_key(), &bob.public_key(), "hello").unwrap();
+/// let message = nip44::decrypt(bob.secret_key(), &alice.public_key(), &payload).unwrap();
+/// assert_eq!(message, "hello");
+/// ```
+pub fn encrypt(
+    secret_key: &SecretKey,
+    public_key: &PublicKey,
+    plaintext: &str,
+) -> Result<String, Error> {
+    let nonce: [u8; 32] = rand::thread_rng().gen();
+
+    encrypt_with_conversation_key(
+        &ConversationKey::derive(secret_key, public_key),
+        plaintext,
+        &nonce,
+    )
+}
+
+/// Decrypt a payload exchanged between `secret_key` and `public_key`
+pub fn decrypt(
+    secret_key: &SecretKey,
+    public_key: &PublicKey,
+    payload: &str,
+) -> Result<String, Error> {
+    decrypt_with_conversation_key(&ConversationKey::derive(secret_key, public_key), payload)
+}
@@ -0,0 +1,150 @@
+use chrono::Utc;
+use rand::Rng;
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{
+    error::Error,
+    event_methods::{get_event_hash, sign_event, SignedEvent, UnsignedEvent},
+    keys::Keys,
+    kind::Kind,
+    types::PublicKey,
+};
+
+/// Seals and gift wraps are dated up to two days in the past
+const MAX_TIMESTAMP_TWEAK: i64 = 2 * 24 * 60 * 60;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum Nip59Error {
+    #[error("Expected a kind {expected} event, found kind {found}")]
+    WrongKind { expected: Kind, found: Kind },
+
+    #[error("The seal was not signed by the author of the rumor")]
+    SenderMismatch,
+
+    #[error("Invalid rumor: {0}")]
+    InvalidRumor(String),
+}
+
+/// Sender and rumor of an unwrapped gift wrap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnwrappedGift {
+    pub sender: PublicKey,
+    pub rumor: UnsignedEvent,
+}
+
+fn random_timestamp() -> i64 {
+    Utc::now().timestamp() - rand::thread_rng().gen_range(0..MAX_TIMESTAMP_TWEAK)
+}
+
+fn check_kind(event: &SignedEvent, expected: Kind) -> Result<(), Nip59Error> {
+    if event.kind != expected {
+        return Err(Nip59Error::WrongKind {
+            expected,
+            found: event.kind,
+        });
+    }
+
+    Ok(())
+}
+
+/// Serialize a rumor with its id, rumors are never signed
+fn rumor_to_json(rumor: &UnsignedEvent) -> Result<String, Error> {
+    let mut value = serde_json::to_value(rumor)?;
+    value["id"] = Value::String(get_event_hash(rumor)?.to_hex());
+
+    Ok(value.to_string())
+}
+
+fn rumor_from_json(json: &str) -> Result<UnsignedEvent, Error> {
+    let value: Value = serde_json::from_str(json)?;
+    let rumor: UnsignedEvent = serde_json::from_value(value.clone())?;
+
+    if let Some(id) = value.get("id") {
+        if id.as_str() != Some(&get_event_hash(&rumor)?.to_hex()) {
+            return Err(
+                Nip59Error::InvalidRumor("id does not match its content".to_string()).into(),
+            );
+        }
+    }
+
+    Ok(rumor)
+}
+
+/// Encrypt a rumor to `receiver` in a seal (kind 13) signed by `sender`
+pub fn seal(
+    sender: &Keys,
+    receiver: &PublicKey,
+    rumor: &UnsignedEvent,
+) -> Result<SignedEvent, Error> {
+    if rumor.pubkey != sender.public_key() {
+        return Err(Nip59Error::SenderMismatch.into());
+    }
+
+    let seal = UnsignedEvent {
+        content: sender.nip44_encrypt(receiver, &rumor_to_json(rumor)?)?,
+        created_at: random_timestamp(),
+        kind: Kind::Seal,
+        pubkey: sender.public_key(),
+        tags: Vec::new(),
+    };
+
+    sign_event(&seal, sender)
+}
+
+/// Seal a rumor and wrap it in a gift wrap (kind 1059) signed by a one-time key
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{event_builder::EventBuilder, keys::Keys, nip59};
+///
+/// let alice = Keys::generate();
+/// let bob = Keys::generate();
+///
+/// let rumor = EventBuilder::private_direct_message(&bob.public_key(), "hello")
+///     .to_unsigned_event(&alice.public_key());
+/// let gift_wrap = nip59::gift_wrap(&alice, &bob.public_key(), &rumor).unwrap();
+///
+/// let unwrapped = nip59::unwrap_gift_wrap(&bob, &gift_wrap).unwrap();
+/// assert_eq!(unwrapped.sender, alice.public_key());
+/// assert_eq!(unwrapped.rumor, rumor);
+/// ```
+pub fn gift_wrap(
+    sender: &Keys,
+    receiver: &PublicKey,
+    rumor: &UnsignedEvent,
+) -> Result<SignedEvent, Error> {
+    let seal = seal(sender, receiver, rumor)?;
+    let ephemeral = Keys::generate();
+
+    let gift_wrap = UnsignedEvent {
+        content: ephemeral.nip44_encrypt(receiver, &serde_json::to_string(&seal)?)?,
+        created_at: random_timestamp(),
+        kind: Kind::GiftWrap,
+        pubkey: ephemeral.public_key(),
+        tags: vec![vec!["p".to_string(), receiver.to_hex()]],
+    };
+
+    sign_event(&gift_wrap, &ephemeral)
+}
+
+/// Decrypt a gift wrap sent to `receiver`, verify the seal and return its sender and rumor
+pub fn unwrap_gift_wrap(receiver: &Keys, gift_wrap: &SignedEvent) -> Result<UnwrappedGift, Error> {
+    check_kind(gift_wrap, Kind::GiftWrap)?;
+    gift_wrap.verify()?;
+
+    let seal: SignedEvent =
+        serde_json::from_str(&receiver.nip44_decrypt(&gift_wrap.pubkey, &gift_wrap.content)?)?;
+    check_kind(&seal, Kind::Seal)?;
+    seal.verify()?;
+
+    let rumor = rumor_from_json(&receiver.nip44_decrypt(&seal.pubkey, &seal.content)?)?;
+
+    if rumor.pubkey != seal.pubkey {
+        return Err(Nip59Error::SenderMismatch.into());
+    }
+
+    Ok(UnwrappedGift {
+        sender: seal.pubkey,
+        rumor,
+    })
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+
+use super::client::ClientError;
+use crate::websocket::{
+    client_message::ClientMessage,
+    relay::{Notification, ReconnectOptions, Relay, RelayStatus},
+};
+
+/// Capacity of the notification channel shared by the relays
+const NOTIFICATION_CHANNEL_SIZE: usize = 4096;
+
+/// Roles of a relay in the pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelayOptions {
+    /// Subscriptions are sent to the relay
+    pub read: bool,
+    /// Events are published to the relay
+    pub write: bool,
+    /// The relay is used to look up other users' relay lists and profiles
+    pub discovery: bool,
+    /// The relay receives the events addressed to the user (direct messages, mentions...)
+    pub inbox: bool,
+}
+
+impl Default for RelayOptions {
+    fn default() -> Self {
+        Self {
+            read: true,
+            write: true,
+            discovery: false,
+            inbox: false,
+        }
+    }
+}
+
+impl RelayOptions {
+    /// Only used for subscriptions
+    pub fn read_only() -> Self {
+        Self {
+            write: false,
+            ..Default::default()
+        }
+    }
+
+    /// Only used to publish events
+    pub fn write_only() -> Self {
+        Self {
+            read: false,
+            ..Default::default()
+        }
+    }
+}
+
+struct PoolRelay {
+    relay: Relay,
+    options: RelayOptions,
+}
+
+/// RelayPool holds the relays of a client along with their roles
+pub struct RelayPool {
+    relays: HashMap<String, PoolRelay>,
+    notifications: broadcast::Sender<Notification>,
+}
+
+impl Default for RelayPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RelayPool {
+    pub fn new() -> Self {
+        let (notifications, _) = broadcast::channel(NOTIFICATION_CHANNEL_SIZE);
+
+        Self {
+            relays: HashMap::new(),
+            notifications,
+        }
+    }
+
+    /// Connect to a relay and add it to the pool
+    pub async fn add_relay(
+        &mut self,
+        url: &str,
+        options: RelayOptions,
+        reconnect_options: ReconnectOptions,
+    ) -> Result<(), ClientError> {
+        // Check if relay is already added
+        if self.relays.contains_key(url) {
+            return Err(ClientError::AlreadySubscribed);
+        }
+
+        let relay = Relay::connect(url, self.notifications.clone(), reconnect_options).await?;
+        self.relays
+            .insert(url.to_string(), PoolRelay { relay, options });
+
+        Ok(())
+    }
+
+    /// Disconnect from a relay and remove it from the pool
+    pub async fn remove_relay(&mut self, url: &str) -> Result<(), ClientError> {
+        match self.relays.remove(url) {
+            Some(pool_relay) => pool_relay.relay.disconnect().await,
+            None => return Err(ClientError::RelayDoesNotExist),
+        }
+
+        Ok(())
+    }
+
+    pub fn relay(&self, url: &str) -> Option<&Relay> {
+        self.relays.get(url).map(|pool_relay| &pool_relay.relay)
+    }
+
+    pub fn relay_options(&self, url: &str) -> Option<RelayOptions> {
+        self.relays.get(url).map(|pool_relay| pool_relay.options)
+    }
+
+    /// Change the roles of a relay already in the pool
+    pub fn set_relay_options(
+        &mut self,
+        url: &str,
+        options: RelayOptions,
+    ) -> Result<(), ClientError> {
+        match self.relays.get_mut(url) {
+            Some(pool_relay) => pool_relay.options = options,
+            None => return Err(ClientError::RelayDoesNotExist),
+        }
+
+        Ok(())
+    }
+
+    /// Urls of every relay in the pool
+    pub fn urls(&self) -> Vec<String> {
+        self.relays.keys().cloned().collect()
+    }
+
+    /// Urls of the relays whose options match the predicate
+    pub fn urls_with(&self, predicate: impl Fn(&RelayOptions) -> bool) -> Vec<String> {
+        self.relays
+            .iter()
+            .filter(|(_, pool_relay)| predicate(&pool_relay.options))
+            .map(|(url, _)| url.to_string())
+            .collect()
+    }
+
+    pub fn read_relays(&self) -> Vec<String> {
+        self.urls_with(|options| options.read)
+    }
+
+    pub fn write_relays(&self) -> Vec<String> {
+        self.urls_with(|options| options.write)
+    }
+
+    pub fn discovery_relays(&self) -> Vec<String> {
+        self.urls_with(|options| options.discovery)
+    }
+
+    pub fn inbox_relays(&self) -> Vec<String> {
+        self.urls_with(|options| options.inbox)
+    }
+
+    /// Get a new receiver of the `(relay_url, message)` notifications sent by every relay
+    pub fn notifications(&self) -> broadcast::Receiver<Notification> {
+        self.notifications.subscribe()
+    }
+
+    /// Send a message to the given relays, fails without sending anything if one of them is not in the pool
+    ///
+    /// Terminated relays are skipped, returns the urls of the relays the message was queued to.
+    pub fn send_to<S: AsRef<str>>(
+        &self,
+        urls: &[S],
+        message: ClientMessage,
+    ) -> Result<Vec<String>, ClientError> {
+        if urls
+            .iter()
+            .any(|url| !self.relays.contains_key(url.as_ref()))
+        {
+            return Err(ClientError::RelayDoesNotExist);
+        }
+
+        let mut sent = Vec::new();
+        for url in urls {
+            let relay = &self.relays[url.as_ref()].relay;
+
+            // Sending only fails once the background task of the relay ended
+            if relay.status() != RelayStatus::Terminated && relay.send(message.clone()).is_ok() {
+                sent.push(url.as_ref().to_string());
+            }
+        }
+
+        Ok(sent)
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::{
+    error::Error,
+    event_methods::{sign_event, SignedEvent, UnsignedEvent},
+    keys::Keys,
+    nip04,
+    types::PublicKey,
+};
+
+/// NostrSigner signs events and encrypts messages on behalf of a user.
+///
+/// The crate implements it for [`Keys`], other implementations can keep the secret key out of
+/// the process (a remote bunker, an HSM...) and be used anywhere an `Arc<dyn NostrSigner>` is.
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{
+///     event_builder::EventBuilder, keys::Keys, signer::NostrSigner,
+/// };
+/// use std::sync::Arc;
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let signer: Arc<dyn NostrSigner> = Arc::new(Keys::generate());
+///     let event = EventBuilder::text_note("hello").sign(&signer).await.unwrap();
+///
+///     assert_eq!(event.pubkey, signer.get_public_key().await.unwrap());
+/// }
+/// ```
+#[async_trait]
+pub trait NostrSigner: Send + Sync {
+    async fn get_public_key(&self) -> Result<PublicKey, Error>;
+
+    /// Sign an event, its pubkey must be the public key of the signer
+    async fn sign_event(&self, event: UnsignedEvent) -> Result<SignedEvent, Error>;
+
+    async fn nip04_encrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error>;
+
+    async fn nip04_decrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error>;
+
+    async fn nip44_encrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error>;
+
+    async fn nip44_decrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error>;
+}
+
+#[async_trait]
+impl NostrSigner for Keys {
+    async fn get_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.public_key())
+    }
+
+    async fn sign_event(&self, event: UnsignedEvent) -> Result<SignedEvent, Error> {
+        sign_event(&event, self)
+    }
+
+    async fn nip04_encrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        nip04::encrypt(self.secret_key(), public_key, content)
+    }
+
+    async fn nip04_decrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        nip04::decrypt(self.secret_key(), public_key, content)
+    }
+
+    async fn nip44_encrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        Keys::nip44_encrypt(self, public_key, content)
+    }
+
+    async fn nip44_decrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        Keys::nip44_decrypt(self, public_key, content)
+    }
+}
+
+#[async_trait]
+impl<T: NostrSigner + ?Sized> NostrSigner for Arc<T> {
+    async fn get_public_key(&self) -> Result<PublicKey, Error> {
+        (**self).get_public_key().await
+    }
+
+    async fn sign_event(&self, event: UnsignedEvent) -> Result<SignedEvent, Error> {
+        (**self).sign_event(event).await
+    }
+
+    async fn nip04_encrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        (**self).nip04_encrypt(public_key, content).await
+    }
+
+    async fn nip04_decrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        (**self).nip04_decrypt(public_key, content).await
+    }
+
+    async fn nip44_encrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        (**self).nip44_encrypt(public_key, content).await
+    }
+
+    async fn nip44_decrypt(&self, public_key: &PublicKey, content: &str) -> Result<String, Error> {
+        (**self).nip44_decrypt(public_key, content).await
+    }
+}
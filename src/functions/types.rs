@@ -0,0 +1,384 @@
+use secp256k1::{schnorr, Secp256k1, XOnlyPublicKey};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use zeroize::{ZeroizeOnDrop, Zeroizing};
+
+use super::{
+    error::Error,
+    utils::{bech32_decode, bech32_encode, Prefix},
+};
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    #[error("Invalid hex value {0}")]
+    InvalidHex(String),
+
+    #[error("Invalid length, expected {expected} bytes, found {found}")]
+    InvalidLength { expected: usize, found: usize },
+
+    #[error("Invalid bech32 value {0}")]
+    InvalidBech32(String),
+
+    #[error("Expected a {expected} bech32 value, found {found}")]
+    WrongPrefix { expected: String, found: String },
+
+    #[error("Invalid key or signature: {0}")]
+    Secp256k1(#[from] secp256k1::Error),
+}
+
+/// Decode a hex string of the given length in bytes
+fn decode_hex(value: &str, expected: usize) -> Result<Vec<u8>, ParseError> {
+    let bytes = hex::decode(value).map_err(|_| ParseError::InvalidHex(value.to_string()))?;
+
+    if bytes.len() != expected {
+        return Err(ParseError::InvalidLength {
+            expected,
+            found: bytes.len(),
+        });
+    }
+
+    Ok(bytes)
+}
+
+/// Decode a bech32 string with the given prefix
+pub(crate) fn decode_bech32(value: &str, prefix: Prefix) -> Result<Vec<u8>, ParseError> {
+    let (hrp, data) =
+        bech32_decode(value).map_err(|_| ParseError::InvalidBech32(value.to_string()))?;
+
+    if hrp != prefix.to_string() {
+        return Err(ParseError::WrongPrefix {
+            expected: prefix.to_string(),
+            found: hrp,
+        });
+    }
+
+    Ok(data)
+}
+
+/// Values that are not hex are parsed as bech32
+fn is_hex(value: &str) -> bool {
+    value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Implement Display, FromStr, Serialize and Deserialize as lowercase hex
+macro_rules! impl_hex {
+    ($type:ty) => {
+        impl fmt::Display for $type {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.to_hex())
+            }
+        }
+
+        impl FromStr for $type {
+            type Err = ParseError;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                Self::parse(value)
+            }
+        }
+
+        impl Serialize for $type {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&self.to_hex())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $type {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                Self::from_hex(&value).map_err(D::Error::custom)
+            }
+        }
+    };
+}
+
+/// Implement a conversion trait for the type itself and for hex or bech32 strings
+macro_rules! impl_into {
+    ($trait:ident, $method:ident, $type:ty) => {
+        impl $trait for $type {
+            fn $method(self) -> Result<$type, ParseError> {
+                Ok(self)
+            }
+        }
+
+        impl $trait for &$type {
+            fn $method(self) -> Result<$type, ParseError> {
+                Ok(*self)
+            }
+        }
+
+        impl $trait for &str {
+            fn $method(self) -> Result<$type, ParseError> {
+                <$type>::parse(self)
+            }
+        }
+
+        impl $trait for String {
+            fn $method(self) -> Result<$type, ParseError> {
+                <$type>::parse(&self)
+            }
+        }
+
+        impl $trait for &String {
+            fn $method(self) -> Result<$type, ParseError> {
+                <$type>::parse(self)
+            }
+        }
+    };
+}
+
+/// Value usable as an event id: an [`EventId`], or a hex or `note` bech32 string
+pub trait IntoEventId {
+    fn into_event_id(self) -> Result<EventId, ParseError>;
+}
+
+/// Value usable as a public key: a [`PublicKey`], or a hex or `npub` bech32 string
+pub trait IntoPublicKey {
+    fn into_public_key(self) -> Result<PublicKey, ParseError>;
+}
+
+/// 32-byte sha256 id of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId([u8; 32]);
+
+impl EventId {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ParseError> {
+        let bytes = bytes.try_into().map_err(|_| ParseError::InvalidLength {
+            expected: 32,
+            found: bytes.len(),
+        })?;
+
+        Ok(Self(bytes))
+    }
+
+    pub fn from_hex(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&decode_hex(value, 32)?)
+    }
+
+    /// Parse a `note` bech32 id
+    pub fn from_bech32(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&decode_bech32(value, Prefix::Note)?)
+    }
+
+    /// Parse a hex or `note` bech32 id
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        if is_hex(value) {
+            Self::from_hex(value)
+        } else {
+            Self::from_bech32(value)
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        bech32_encode(Prefix::Note, &self.0)
+    }
+}
+
+impl_hex!(EventId);
+impl_into!(IntoEventId, into_event_id, EventId);
+
+/// 32-byte x-only public key of a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PublicKey(XOnlyPublicKey);
+
+impl PublicKey {
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() != 32 {
+            return Err(ParseError::InvalidLength {
+                expected: 32,
+                found: bytes.len(),
+            });
+        }
+
+        Ok(Self(XOnlyPublicKey::from_slice(bytes)?))
+    }
+
+    pub fn from_hex(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&decode_hex(value, 32)?)
+    }
+
+    /// Parse a `npub` bech32 key
+    pub fn from_bech32(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&decode_bech32(value, Prefix::Npub)?)
+    }
+
+    /// Parse a hex or `npub` bech32 key
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        if is_hex(value) {
+            Self::from_hex(value)
+        } else {
+            Self::from_bech32(value)
+        }
+    }
+
+    pub fn x_only_public_key(&self) -> XOnlyPublicKey {
+        self.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.serialize()
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        bech32_encode(Prefix::Npub, &self.to_bytes())
+    }
+}
+
+impl From<XOnlyPublicKey> for PublicKey {
+    fn from(public_key: XOnlyPublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+impl_hex!(PublicKey);
+impl_into!(IntoPublicKey, into_public_key, PublicKey);
+
+/// 32-byte secret key of a user, erased from memory when it is dropped
+///
+/// The key is kept as plain bytes, the secp256k1 types are only built when needed and erased
+/// right after use with their `non_secure_erase`. The erasure is best effort: the compiler may
+/// leave copies of those values on the stack, and the values returned by
+/// [`SecretKey::secp256k1_secret_key`], [`SecretKey::to_hex`] and [`SecretKey::to_bech32`] are
+/// not erased.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretKey(Zeroizing<[u8; 32]>);
+
+impl SecretKey {
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() != 32 {
+            return Err(ParseError::InvalidLength {
+                expected: 32,
+                found: bytes.len(),
+            });
+        }
+
+        // Rejects zero and values out of the curve order
+        secp256k1::SecretKey::from_slice(bytes)?.non_secure_erase();
+
+        let mut secret = Zeroizing::new([0u8; 32]);
+        secret.copy_from_slice(bytes);
+        Ok(Self(secret))
+    }
+
+    pub fn from_hex(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&Zeroizing::new(decode_hex(value, 32)?))
+    }
+
+    /// Parse a `nsec` bech32 key
+    pub fn from_bech32(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&Zeroizing::new(decode_bech32(value, Prefix::Nsec)?))
+    }
+
+    /// Parse a hex or `nsec` bech32 key
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        if is_hex(value) {
+            Self::from_hex(value)
+        } else {
+            Self::from_bech32(value)
+        }
+    }
+
+    /// Build the secp256k1 secret key, call `non_secure_erase` on it once done
+    pub fn secp256k1_secret_key(&self) -> secp256k1::SecretKey {
+        secp256k1::SecretKey::from_slice(self.secret_bytes())
+            .expect("the key was validated when created")
+    }
+
+    pub fn secret_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Return the public key matching this secret key
+    pub fn public_key(&self) -> PublicKey {
+        let secp = Secp256k1::signing_only();
+        let mut secret_key = self.secp256k1_secret_key();
+        let (public_key, _) = secret_key.x_only_public_key(&secp);
+        secret_key.non_secure_erase();
+
+        PublicKey(public_key)
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.secret_bytes())
+    }
+
+    pub fn to_bech32(&self) -> Result<String, Error> {
+        bech32_encode(Prefix::Nsec, self.secret_bytes())
+    }
+}
+
+impl From<secp256k1::SecretKey> for SecretKey {
+    fn from(secret_key: secp256k1::SecretKey) -> Self {
+        Self(Zeroizing::new(secret_key.secret_bytes()))
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+impl ZeroizeOnDrop for SecretKey {}
+
+impl_hex!(SecretKey);
+
+/// 64-byte schnorr signature of an event id
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Signature(schnorr::Signature);
+
+impl Signature {
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() != 64 {
+            return Err(ParseError::InvalidLength {
+                expected: 64,
+                found: bytes.len(),
+            });
+        }
+
+        Ok(Self(schnorr::Signature::from_slice(bytes)?))
+    }
+
+    pub fn from_hex(value: &str) -> Result<Self, ParseError> {
+        Self::from_slice(&decode_hex(value, 64)?)
+    }
+
+    /// Signatures have no bech32 encoding, only hex is accepted
+    pub fn parse(value: &str) -> Result<Self, ParseError> {
+        Self::from_hex(value)
+    }
+
+    pub fn schnorr_signature(&self) -> schnorr::Signature {
+        self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0.as_ref())
+    }
+}
+
+impl From<schnorr::Signature> for Signature {
+    fn from(signature: schnorr::Signature) -> Self {
+        Self(signature)
+    }
+}
+
+impl_hex!(Signature);
@@ -1,9 +1,21 @@
-use bech32::{ToBase32, Variant};
+use bech32::{FromBase32, ToBase32, Variant};
 use rand::Rng;
+use secp256k1::{ecdh, Parity};
+use zeroize::Zeroizing;
+
+use super::{
+    error::Error,
+    types::{PublicKey, SecretKey},
+};
 
 pub enum Prefix {
     Npub,
     Nsec,
+    Note,
+    Nprofile,
+    Nevent,
+    Naddr,
+    Nrelay,
 }
 
 // Display 'trait' needed for enum "to_string()"
@@ -12,19 +24,29 @@ impl std::fmt::Display for Prefix {
         match self {
             Prefix::Npub => write!(f, "npub"),
             Prefix::Nsec => write!(f, "nsec"),
+            Prefix::Note => write!(f, "note"),
+            Prefix::Nprofile => write!(f, "nprofile"),
+            Prefix::Nevent => write!(f, "nevent"),
+            Prefix::Naddr => write!(f, "naddr"),
+            Prefix::Nrelay => write!(f, "nrelay"),
         }
     }
 }
-/// Converts a hex encoded string to bech32 format for given a Prefix (hrp)
-pub fn bech32_encode(hrp: Prefix, hex_key: &String) -> String {
-    bech32::encode(
+/// Converts bytes to bech32 format for given a Prefix (hrp)
+pub fn bech32_encode(hrp: Prefix, data: &[u8]) -> Result<String, Error> {
+    Ok(bech32::encode(
         &hrp.to_string(),
-        hex::decode(hex_key)
-            .expect(&("could not decode provided key/note id=".to_owned() + hex_key))
-            .to_base32(),
+        data.to_base32(),
         Variant::Bech32,
-    )
-    .expect("Could not bech32-encode data")
+    )?)
+}
+
+/// Decodes a bech32 string into its hrp and data
+pub fn bech32_decode(data: &str) -> Result<(String, Vec<u8>), Error> {
+    let (hrp, data, _) = bech32::decode(data)?;
+    let data = Vec::<u8>::from_base32(&data)?;
+
+    Ok((hrp, data))
 }
 
 pub fn random_hash() -> String {
@@ -33,3 +55,16 @@ pub fn random_hash() -> String {
     rng.fill(&mut bytes);
     hex::encode(bytes)
 }
+
+/// Unhashed x coordinate of the ECDH point of two keys, used by NIP-04 and NIP-44
+pub fn shared_secret(secret_key: &SecretKey, public_key: &PublicKey) -> Zeroizing<[u8; 32]> {
+    let public_key =
+        secp256k1::PublicKey::from_x_only_public_key(public_key.x_only_public_key(), Parity::Even);
+    let mut secp_secret_key = secret_key.secp256k1_secret_key();
+    let point = Zeroizing::new(ecdh::shared_secret_point(&public_key, &secp_secret_key));
+    secp_secret_key.non_secure_erase();
+
+    let mut secret = Zeroizing::new([0u8; 32]);
+    secret.copy_from_slice(&point[..32]);
+    secret
+}
@@ -2,9 +2,27 @@ mod functions;
 mod websocket;
 pub use functions::client;
 pub use functions::convert_key::ConvertKey;
+pub use functions::error::Error;
+pub use functions::event_builder;
 pub use functions::event_methods;
 pub use functions::generate_private_key::GeneratePrivateKey;
 pub use functions::generate_public_key::GeneratePublicKey;
+pub use functions::keys;
+pub use functions::kind;
+pub use functions::nip04;
 pub use functions::nip05_query::Nip05Query;
+pub use functions::nip06;
+pub use functions::nip19;
+pub use functions::nip21;
+pub use functions::nip44;
+pub use functions::nip59;
+pub use functions::relay_pool;
+pub use functions::signer;
+pub use functions::types;
+pub use websocket::client_message;
+pub use websocket::relay;
+pub use websocket::relay_message;
 pub use websocket::req;
+#[cfg(feature = "test-relay")]
+pub use websocket::test_relay;
 pub use websocket::ws;
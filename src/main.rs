@@ -3,11 +3,14 @@
 use std::vec;
 
 use rusted_nostr_tools::client::Client;
+use rusted_nostr_tools::kind::Kind;
+use rusted_nostr_tools::relay_message::{RelayMessage, RelayMessageError};
 use rusted_nostr_tools::req::ReqFilter;
 
-use tungstenite::Message;
-
-fn handle_message(relay_url: &String, message: &Message) -> Result<(), String> {
+fn handle_message(
+    relay_url: &String,
+    message: &Result<RelayMessage, RelayMessageError>,
+) -> Result<(), String> {
     println!("Received message from {}: {:?}", relay_url, message);
 
     println!("Events: {:?}", message);
@@ -33,14 +36,9 @@ async fn main() {
     // Subscribe to my last text note
     let subscription_id = nostr_client
         .subscribe(vec![ReqFilter {
-            ids: None,
-            authors: None,
-            kinds: Some(vec![0]),
-            e: None,
-            p: None,
-            since: None,
-            until: None,
+            kinds: Some(vec![Kind::Metadata]),
             limit: Some(10),
+            ..Default::default()
         }])
         .await
         .unwrap();
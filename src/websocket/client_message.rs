@@ -0,0 +1,203 @@
+use crate::functions::event_methods::SignedEvent;
+use serde_json::{json, Value};
+use thiserror::Error;
+use tungstenite::Message;
+
+use super::req::ReqFilter;
+
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum ClientMessageError {
+    #[error("Error parsing the client message as a JSON array")]
+    InvalidJson,
+
+    #[error("The client message is missing its type")]
+    MissingType,
+
+    #[error("Unknown client message type: {0}")]
+    UnknownType(String),
+
+    #[error("Malformed {0} message")]
+    Malformed(&'static str),
+}
+
+/// ClientMessage is a message sent from a client to a relay.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientMessage {
+    /// `["EVENT", <event JSON>]` used to publish events.
+    Event(Box<SignedEvent>),
+    /// `["REQ", <subscription_id>, <filters1>, <filters2>, ...]` used to request events and subscribe to new updates.
+    Req {
+        subscription_id: String,
+        filters: Vec<ReqFilter>,
+    },
+    /// `["CLOSE", <subscription_id>]` used to stop previous subscriptions.
+    Close { subscription_id: String },
+    /// `["AUTH", <signed event JSON>]` used to authenticate to a relay (NIP-42).
+    Auth(Box<SignedEvent>),
+    /// `["COUNT", <subscription_id>, <filters1>, <filters2>, ...]` used to request event counts (NIP-45).
+    Count {
+        subscription_id: String,
+        filters: Vec<ReqFilter>,
+    },
+    /// `["NEG-OPEN", <subscription_id>, <filter>, <initial_message>]` used to start a negentropy sync (NIP-77).
+    NegOpen {
+        subscription_id: String,
+        filter: Box<ReqFilter>,
+        initial_message: String,
+    },
+    /// `["NEG-MSG", <subscription_id>, <message>]` used to continue a negentropy sync (NIP-77).
+    NegMsg {
+        subscription_id: String,
+        message: String,
+    },
+    /// `["NEG-CLOSE", <subscription_id>]` used to end a negentropy sync (NIP-77).
+    NegClose { subscription_id: String },
+}
+
+impl ClientMessage {
+    /// Return the serialized message
+    pub fn to_json(&self) -> String {
+        let value = match self {
+            Self::Event(event) => json!(["EVENT", event]),
+            Self::Req {
+                subscription_id,
+                filters,
+            } => Self::with_filters("REQ", subscription_id, filters),
+            Self::Close { subscription_id } => json!(["CLOSE", subscription_id]),
+            Self::Auth(event) => json!(["AUTH", event]),
+            Self::Count {
+                subscription_id,
+                filters,
+            } => Self::with_filters("COUNT", subscription_id, filters),
+            Self::NegOpen {
+                subscription_id,
+                filter,
+                initial_message,
+            } => json!([
+                "NEG-OPEN",
+                subscription_id,
+                filter.to_json(),
+                initial_message
+            ]),
+            Self::NegMsg {
+                subscription_id,
+                message,
+            } => json!(["NEG-MSG", subscription_id, message]),
+            Self::NegClose { subscription_id } => json!(["NEG-CLOSE", subscription_id]),
+        };
+
+        value.to_string()
+    }
+
+    /// Parse a client message from its JSON representation
+    pub fn from_json(json: &str) -> Result<Self, ClientMessageError> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|_| ClientMessageError::InvalidJson)?;
+        let array = value.as_array().ok_or(ClientMessageError::InvalidJson)?;
+
+        let message_type = array
+            .first()
+            .and_then(Value::as_str)
+            .ok_or(ClientMessageError::MissingType)?;
+
+        match message_type {
+            "EVENT" => match array.as_slice() {
+                [_, event] => Ok(Self::Event(Box::new(Self::parse_event(event, "EVENT")?))),
+                _ => Err(ClientMessageError::Malformed("EVENT")),
+            },
+            "REQ" => {
+                let (subscription_id, filters) = Self::parse_filters(array, "REQ")?;
+
+                Ok(Self::Req {
+                    subscription_id,
+                    filters,
+                })
+            }
+            "CLOSE" => match array.as_slice() {
+                [_, Value::String(subscription_id)] => Ok(Self::Close {
+                    subscription_id: subscription_id.to_string(),
+                }),
+                _ => Err(ClientMessageError::Malformed("CLOSE")),
+            },
+            "AUTH" => match array.as_slice() {
+                [_, event] => Ok(Self::Auth(Box::new(Self::parse_event(event, "AUTH")?))),
+                _ => Err(ClientMessageError::Malformed("AUTH")),
+            },
+            "COUNT" => {
+                let (subscription_id, filters) = Self::parse_filters(array, "COUNT")?;
+
+                Ok(Self::Count {
+                    subscription_id,
+                    filters,
+                })
+            }
+            "NEG-OPEN" => match array.as_slice() {
+                [_, Value::String(subscription_id), filter, Value::String(initial_message)] => {
+                    let filter = serde_json::from_value::<ReqFilter>(filter.clone())
+                        .map_err(|_| ClientMessageError::Malformed("NEG-OPEN"))?;
+
+                    Ok(Self::NegOpen {
+                        subscription_id: subscription_id.to_string(),
+                        filter: Box::new(filter),
+                        initial_message: initial_message.to_string(),
+                    })
+                }
+                _ => Err(ClientMessageError::Malformed("NEG-OPEN")),
+            },
+            "NEG-MSG" => match array.as_slice() {
+                [_, Value::String(subscription_id), Value::String(message)] => Ok(Self::NegMsg {
+                    subscription_id: subscription_id.to_string(),
+                    message: message.to_string(),
+                }),
+                _ => Err(ClientMessageError::Malformed("NEG-MSG")),
+            },
+            "NEG-CLOSE" => match array.as_slice() {
+                [_, Value::String(subscription_id)] => Ok(Self::NegClose {
+                    subscription_id: subscription_id.to_string(),
+                }),
+                _ => Err(ClientMessageError::Malformed("NEG-CLOSE")),
+            },
+            other => Err(ClientMessageError::UnknownType(other.to_string())),
+        }
+    }
+
+    /// Return the message as a websocket text frame
+    pub fn to_message(&self) -> Message {
+        Message::text(self.to_json())
+    }
+
+    fn with_filters(message_type: &str, subscription_id: &str, filters: &[ReqFilter]) -> Value {
+        let mut value = json!([message_type, subscription_id]);
+        for filter in filters {
+            value.as_array_mut().unwrap().push(filter.to_json());
+        }
+
+        value
+    }
+
+    fn parse_event(
+        event: &Value,
+        message_type: &'static str,
+    ) -> Result<SignedEvent, ClientMessageError> {
+        serde_json::from_value::<SignedEvent>(event.clone())
+            .map_err(|_| ClientMessageError::Malformed(message_type))
+    }
+
+    fn parse_filters(
+        array: &[Value],
+        message_type: &'static str,
+    ) -> Result<(String, Vec<ReqFilter>), ClientMessageError> {
+        let subscription_id = match array.get(1) {
+            Some(Value::String(subscription_id)) => subscription_id.to_string(),
+            _ => return Err(ClientMessageError::Malformed(message_type)),
+        };
+
+        let filters = array[2..]
+            .iter()
+            .map(|filter| serde_json::from_value::<ReqFilter>(filter.clone()))
+            .collect::<Result<Vec<ReqFilter>, _>>()
+            .map_err(|_| ClientMessageError::Malformed(message_type))?;
+
+        Ok((subscription_id, filters))
+    }
+}
@@ -1,2 +1,7 @@
+pub mod client_message;
+pub mod relay;
+pub mod relay_message;
 pub mod req;
+#[cfg(feature = "test-relay")]
+pub mod test_relay;
 pub mod ws;
@@ -0,0 +1,367 @@
+use futures::FutureExt;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{
+    client_message::ClientMessage,
+    relay_message::{RelayMessage, RelayMessageError},
+    req::ReqFilter,
+    ws::{SimplifiedWS, SimplifiedWSError},
+};
+
+/// Message received from a relay along with its url, frames that could not be parsed are
+/// forwarded as the error met while parsing them
+pub type Notification = (String, Result<RelayMessage, RelayMessageError>);
+
+/// Connection state of a relay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayStatus {
+    /// Waiting to (re)connect to the relay
+    Connecting,
+    /// The websocket is open
+    Connected,
+    /// The websocket was closed, a reconnection will be attempted
+    Disconnected,
+    /// The relay was removed or gave up reconnecting, it will not be used anymore
+    Terminated,
+}
+
+/// Options used to reconnect to a relay after the websocket was closed
+#[derive(Debug, Clone)]
+pub struct ReconnectOptions {
+    /// Delay before the first reconnection attempt, doubled after every failed attempt
+    pub initial_delay: Duration,
+    /// Maximum delay between two attempts
+    pub max_delay: Duration,
+    /// Give up after this many failed attempts in a row, None retries forever
+    pub max_attempts: Option<u32>,
+    /// Maximum number of messages queued while disconnected, the oldest are dropped beyond it.
+    /// Subscriptions are not counted, they are always replayed.
+    pub max_pending: usize,
+}
+
+impl Default for ReconnectOptions {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_attempts: None,
+            max_pending: 1000,
+        }
+    }
+}
+
+impl ReconnectOptions {
+    /// Return the jittered delay to wait before the given attempt (starting at 0)
+    fn delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        // Wait between half and all of the delay so clients don't reconnect in lockstep
+        delay.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+}
+
+/// Handle to a relay connection driven by its own background task.
+///
+/// The task forwards every message received from the relay to the shared notification channel
+/// as `(relay_url, message)`, text frames that cannot be parsed into a [`RelayMessage`] are
+/// forwarded as a [`RelayMessageError`] and the other frames are ignored.
+/// When the websocket is closed the task reconnects with exponential backoff and re-sends the
+/// active subscriptions, with `since` set to the `created_at` of the last event received.
+pub struct Relay {
+    url: String,
+    sender: mpsc::UnboundedSender<ClientMessage>,
+    status: watch::Receiver<RelayStatus>,
+    task: JoinHandle<()>,
+}
+
+impl Relay {
+    /// Connect to the relay and spawn the task reading from it
+    pub async fn connect(
+        url: &str,
+        notifications: broadcast::Sender<Notification>,
+        options: ReconnectOptions,
+    ) -> Result<Self, SimplifiedWSError> {
+        let socket = SimplifiedWS::new(url).await?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (status_sender, status) = watch::channel(RelayStatus::Connected);
+
+        let connection = Connection {
+            url: url.to_string(),
+            options,
+            receiver,
+            notifications,
+            status: status_sender,
+            subscriptions: HashMap::new(),
+            last_seen: HashMap::new(),
+            pending: VecDeque::new(),
+        };
+        let task = tokio::spawn(connection.run(socket));
+
+        Ok(Self {
+            url: url.to_string(),
+            sender,
+            status,
+            task,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the current connection state
+    pub fn status(&self) -> RelayStatus {
+        *self.status.borrow()
+    }
+
+    /// Return a receiver notified on every connection state change
+    pub fn status_watch(&self) -> watch::Receiver<RelayStatus> {
+        self.status.clone()
+    }
+
+    /// Queue a message to be sent to the relay, messages sent while disconnected are sent after reconnecting
+    pub fn send(&self, message: ClientMessage) -> Result<(), SimplifiedWSError> {
+        self.sender
+            .send(message)
+            .map_err(|_| SimplifiedWSError::SendMessageError)
+    }
+
+    /// Close the connection and wait for the background task to finish
+    pub async fn disconnect(self) {
+        drop(self.sender);
+        let _ = self.task.await;
+    }
+}
+
+/// State owned by the background task of a relay
+struct Connection {
+    url: String,
+    options: ReconnectOptions,
+    receiver: mpsc::UnboundedReceiver<ClientMessage>,
+    notifications: broadcast::Sender<Notification>,
+    status: watch::Sender<RelayStatus>,
+    /// Active subscriptions, re-sent after reconnecting
+    subscriptions: HashMap<String, Vec<ReqFilter>>,
+    /// `created_at` of the last event received for each subscription
+    last_seen: HashMap<String, i64>,
+    /// Messages queued while disconnected, oldest first
+    pending: VecDeque<ClientMessage>,
+}
+
+impl Connection {
+    async fn run(mut self, mut socket: SimplifiedWS) {
+        loop {
+            if !self.serve(&mut socket).await {
+                break;
+            }
+
+            self.status.send_replace(RelayStatus::Disconnected);
+
+            socket = match self.reconnect().await {
+                Some(socket) => socket,
+                None => break,
+            };
+        }
+
+        self.status.send_replace(RelayStatus::Terminated);
+    }
+
+    /// Forward messages until the websocket is closed, returns false if the handle was dropped
+    async fn serve(&mut self, socket: &mut SimplifiedWS) -> bool {
+        loop {
+            tokio::select! {
+                incoming = socket.read_message() => {
+                    if self.read(incoming) {
+                        return true;
+                    }
+                }
+                outgoing = self.receiver.recv() => match outgoing {
+                    Some(message) => {
+                        self.track(&message);
+
+                        // Notice a closed connection before writing to it
+                        let closed = socket
+                            .read_message()
+                            .now_or_never()
+                            .is_some_and(|incoming| self.read(incoming));
+                        if closed || socket.send_message(&message.to_message()).await.is_err() {
+                            // Send it again after reconnecting
+                            self.queue(message);
+                            return true;
+                        }
+                    }
+                    // The handle was dropped, close the connection
+                    None => {
+                        let _ = socket.socket.close(None).await;
+                        return false;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Handle a frame read from the websocket, returns true if the connection was closed
+    fn read(&mut self, incoming: Result<Message, SimplifiedWSError>) -> bool {
+        match incoming {
+            Ok(Message::Close(_)) | Err(_) => return true,
+            Ok(message) => match RelayMessage::from_message(&message) {
+                Ok(message) => self.received(message),
+                // Pings and pongs are answered by the websocket itself
+                Err(RelayMessageError::NonTextFrame) => {}
+                Err(err) => self.notify(Err(err)),
+            },
+        }
+
+        false
+    }
+
+    /// Reconnect with exponential backoff and replay the subscriptions
+    async fn reconnect(&mut self) -> Option<SimplifiedWS> {
+        let mut attempt = 0;
+
+        loop {
+            if self
+                .options
+                .max_attempts
+                .is_some_and(|max_attempts| attempt >= max_attempts)
+            {
+                return None;
+            }
+
+            self.status.send_replace(RelayStatus::Connecting);
+
+            let delay = tokio::time::sleep(self.options.delay(attempt));
+            tokio::pin!(delay);
+
+            // Keep queuing outgoing messages while waiting
+            loop {
+                tokio::select! {
+                    _ = &mut delay => break,
+                    outgoing = self.receiver.recv() => match outgoing {
+                        Some(message) => {
+                            self.track(&message);
+                            self.queue(message);
+                        }
+                        None => return None,
+                    },
+                }
+            }
+
+            match SimplifiedWS::new(&self.url).await {
+                Ok(mut socket) => {
+                    if self.replay(&mut socket).await.is_ok() {
+                        self.status.send_replace(RelayStatus::Connected);
+                        return Some(socket);
+                    }
+                }
+                Err(_) => {
+                    self.status.send_replace(RelayStatus::Disconnected);
+                }
+            }
+
+            attempt += 1;
+        }
+    }
+
+    /// Re-send the active subscriptions and the messages queued while disconnected
+    async fn replay(&mut self, socket: &mut SimplifiedWS) -> Result<(), SimplifiedWSError> {
+        for (subscription_id, filters) in self.subscriptions.iter() {
+            let mut filters = filters.clone();
+
+            if let Some(last_seen) = self.last_seen.get(subscription_id) {
+                for filter in filters.iter_mut() {
+                    let last_seen = u64::try_from(*last_seen).unwrap_or(0);
+                    filter.since = Some(filter.since.unwrap_or(0).max(last_seen));
+                }
+            }
+
+            let req = ClientMessage::Req {
+                subscription_id: subscription_id.to_string(),
+                filters,
+            };
+            socket.send_message(&req.to_message()).await?;
+        }
+
+        // Messages are only removed once sent, the others are kept for the next attempt
+        while let Some(message) = self.pending.front() {
+            socket.send_message(&message.to_message()).await?;
+            self.pending.pop_front();
+        }
+
+        Ok(())
+    }
+
+    /// Queue a message to send after reconnecting, dropping the oldest one if the queue is full
+    fn queue(&mut self, message: ClientMessage) {
+        // Subscriptions are replayed from `self.subscriptions`
+        if matches!(
+            message,
+            ClientMessage::Req { .. } | ClientMessage::Close { .. }
+        ) {
+            return;
+        }
+
+        if self.pending.len() >= self.options.max_pending {
+            self.pending.pop_front();
+        }
+        if self.options.max_pending > 0 {
+            self.pending.push_back(message);
+        }
+    }
+
+    /// Keep track of the active subscriptions
+    fn track(&mut self, message: &ClientMessage) {
+        match message {
+            ClientMessage::Req {
+                subscription_id,
+                filters,
+            } => {
+                self.subscriptions
+                    .insert(subscription_id.to_string(), filters.clone());
+                self.last_seen.remove(subscription_id);
+            }
+            ClientMessage::Close { subscription_id } => {
+                self.subscriptions.remove(subscription_id);
+                self.last_seen.remove(subscription_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn received(&mut self, message: RelayMessage) {
+        match &message {
+            RelayMessage::Event {
+                subscription_id,
+                event,
+            } if self.subscriptions.contains_key(subscription_id) => {
+                let last_seen = self
+                    .last_seen
+                    .entry(subscription_id.to_string())
+                    .or_insert(event.created_at);
+                *last_seen = (*last_seen).max(event.created_at);
+            }
+            RelayMessage::Closed {
+                subscription_id, ..
+            } => {
+                self.subscriptions.remove(subscription_id);
+                self.last_seen.remove(subscription_id);
+            }
+            _ => {}
+        }
+
+        self.notify(Ok(message));
+    }
+
+    fn notify(&self, message: Result<RelayMessage, RelayMessageError>) {
+        // Sending only fails when nobody is listening
+        let _ = self.notifications.send((self.url.clone(), message));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::functions::{event_methods::SignedEvent, types::EventId};
+use serde_json::{json, Value};
+use thiserror::Error;
+use tungstenite::Message;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum RelayMessageError {
+    #[error("Received a non-text websocket frame")]
+    NonTextFrame,
+
+    #[error("Error parsing the relay message as a JSON array")]
+    InvalidJson,
+
+    #[error("The relay message is missing its type")]
+    MissingType,
+
+    #[error("Unknown relay message type: {0}")]
+    UnknownType(String),
+
+    #[error("Malformed {0} message")]
+    Malformed(&'static str),
+}
+
+/// RelayMessage is a parsed message sent from a relay to the client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelayMessage {
+    /// `["EVENT", <subscription_id>, <event JSON>]` used to send events requested by clients.
+    Event {
+        subscription_id: String,
+        event: Box<SignedEvent>,
+    },
+    /// `["OK", <event_id>, <true|false>, <message>]` used to indicate acceptance or denial of an EVENT message.
+    Ok {
+        event_id: EventId,
+        status: bool,
+        message: String,
+    },
+    /// `["EOSE", <subscription_id>]` used to indicate the end of stored events.
+    Eose { subscription_id: String },
+    /// `["NOTICE", <message>]` used to send human-readable messages to clients.
+    Notice { message: String },
+    /// `["CLOSED", <subscription_id>, <message>]` used to indicate that a subscription was ended on the relay side.
+    Closed {
+        subscription_id: String,
+        message: String,
+    },
+    /// `["AUTH", <challenge>]` used to request authentication from the client.
+    Auth { challenge: String },
+    /// `["COUNT", <subscription_id>, {"count": <integer>}]` used to answer a COUNT request.
+    Count { subscription_id: String, count: u64 },
+}
+
+impl RelayMessage {
+    /// Parse a websocket frame received from a relay
+    pub fn from_message(message: &Message) -> Result<Self, RelayMessageError> {
+        match message {
+            Message::Text(text) => Self::from_json(text),
+            _ => Err(RelayMessageError::NonTextFrame),
+        }
+    }
+
+    /// Parse a relay message from its JSON representation
+    pub fn from_json(json: &str) -> Result<Self, RelayMessageError> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|_| RelayMessageError::InvalidJson)?;
+        let array = value.as_array().ok_or(RelayMessageError::InvalidJson)?;
+
+        let message_type = array
+            .first()
+            .and_then(Value::as_str)
+            .ok_or(RelayMessageError::MissingType)?;
+
+        match message_type {
+            "EVENT" => {
+                let (subscription_id, event) = match array.as_slice() {
+                    [_, Value::String(id), event] => (id, event),
+                    _ => return Err(RelayMessageError::Malformed("EVENT")),
+                };
+                let event = serde_json::from_value::<SignedEvent>(event.clone())
+                    .map_err(|_| RelayMessageError::Malformed("EVENT"))?;
+
+                Ok(Self::Event {
+                    subscription_id: subscription_id.to_string(),
+                    event: Box::new(event),
+                })
+            }
+            "OK" => match array.as_slice() {
+                [_, Value::String(event_id), Value::Bool(status), Value::String(message)] => {
+                    Ok(Self::Ok {
+                        event_id: EventId::from_hex(event_id)
+                            .map_err(|_| RelayMessageError::Malformed("OK"))?,
+                        status: *status,
+                        message: message.to_string(),
+                    })
+                }
+                _ => Err(RelayMessageError::Malformed("OK")),
+            },
+            "EOSE" => match array.as_slice() {
+                [_, Value::String(subscription_id)] => Ok(Self::Eose {
+                    subscription_id: subscription_id.to_string(),
+                }),
+                _ => Err(RelayMessageError::Malformed("EOSE")),
+            },
+            "NOTICE" => match array.as_slice() {
+                [_, Value::String(message)] => Ok(Self::Notice {
+                    message: message.to_string(),
+                }),
+                _ => Err(RelayMessageError::Malformed("NOTICE")),
+            },
+            "CLOSED" => match array.as_slice() {
+                [_, Value::String(subscription_id), Value::String(message)] => Ok(Self::Closed {
+                    subscription_id: subscription_id.to_string(),
+                    message: message.to_string(),
+                }),
+                _ => Err(RelayMessageError::Malformed("CLOSED")),
+            },
+            "AUTH" => match array.as_slice() {
+                [_, Value::String(challenge)] => Ok(Self::Auth {
+                    challenge: challenge.to_string(),
+                }),
+                _ => Err(RelayMessageError::Malformed("AUTH")),
+            },
+            "COUNT" => match array.as_slice() {
+                [_, Value::String(subscription_id), count] => {
+                    let count = count["count"]
+                        .as_u64()
+                        .ok_or(RelayMessageError::Malformed("COUNT"))?;
+
+                    Ok(Self::Count {
+                        subscription_id: subscription_id.to_string(),
+                        count,
+                    })
+                }
+                _ => Err(RelayMessageError::Malformed("COUNT")),
+            },
+            other => Err(RelayMessageError::UnknownType(other.to_string())),
+        }
+    }
+
+    /// Return the serialized message
+    pub fn to_json(&self) -> String {
+        let value = match self {
+            Self::Event {
+                subscription_id,
+                event,
+            } => json!(["EVENT", subscription_id, event]),
+            Self::Ok {
+                event_id,
+                status,
+                message,
+            } => json!(["OK", event_id, status, message]),
+            Self::Eose { subscription_id } => json!(["EOSE", subscription_id]),
+            Self::Notice { message } => json!(["NOTICE", message]),
+            Self::Closed {
+                subscription_id,
+                message,
+            } => json!(["CLOSED", subscription_id, message]),
+            Self::Auth { challenge } => json!(["AUTH", challenge]),
+            Self::Count {
+                subscription_id,
+                count,
+            } => json!(["COUNT", subscription_id, { "count": count }]),
+        };
+
+        value.to_string()
+    }
+
+    /// Return the message as a websocket text frame
+    pub fn to_message(&self) -> Message {
+        Message::text(self.to_json())
+    }
+
+    /// Return the subscription id the message belongs to, if any
+    pub fn subscription_id(&self) -> Option<&str> {
+        match self {
+            Self::Event {
+                subscription_id, ..
+            }
+            | Self::Eose { subscription_id }
+            | Self::Closed {
+                subscription_id, ..
+            }
+            | Self::Count {
+                subscription_id, ..
+            } => Some(subscription_id),
+            _ => None,
+        }
+    }
+}
+
+/// Machine-readable prefix of an `OK` or `CLOSED` message (NIP-01), e.g. `"blocked: you are banned"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineReadablePrefix {
+    Duplicate,
+    Pow,
+    Blocked,
+    RateLimited,
+    Invalid,
+    Restricted,
+    AuthRequired,
+    Mute,
+    Error,
+}
+
+impl MachineReadablePrefix {
+    /// Parse the prefix of a relay message, returns None if the message has no known prefix
+    pub fn parse(message: &str) -> Option<Self> {
+        let (prefix, _) = message.split_once(':')?;
+
+        match prefix {
+            "duplicate" => Some(Self::Duplicate),
+            "pow" => Some(Self::Pow),
+            "blocked" => Some(Self::Blocked),
+            "rate-limited" => Some(Self::RateLimited),
+            "invalid" => Some(Self::Invalid),
+            "restricted" => Some(Self::Restricted),
+            "auth-required" => Some(Self::AuthRequired),
+            "mute" => Some(Self::Mute),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MachineReadablePrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Duplicate => write!(f, "duplicate"),
+            Self::Pow => write!(f, "pow"),
+            Self::Blocked => write!(f, "blocked"),
+            Self::RateLimited => write!(f, "rate-limited"),
+            Self::Invalid => write!(f, "invalid"),
+            Self::Restricted => write!(f, "restricted"),
+            Self::AuthRequired => write!(f, "auth-required"),
+            Self::Mute => write!(f, "mute"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
@@ -1,10 +1,19 @@
-use crate::functions::utils::random_hash;
-use serde::{Deserialize, Serialize};
-use serde_json::json;
+use crate::functions::{
+    event_methods::SignedEvent,
+    kind::Kind,
+    types::{EventId, IntoEventId, IntoPublicKey, ParseError, PublicKey},
+    utils::random_hash,
+};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
 use std::fmt;
+use thiserror::Error;
+
+use super::client_message::ClientMessage;
 
 /// Req struct is used to request events and subscribe to new updates.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Req {
     /// `<subscription_id>` is a random string that should be used to represent a subscription.
     pub subscription_id: String,
@@ -12,21 +21,27 @@ pub struct Req {
     pub filters: Vec<ReqFilter>,
 }
 
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum ReqFilterError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    #[error("Invalid tag name {0}, tag filters must be a single letter")]
+    InvalidTagName(char),
+}
+
 /// ReqFilter is a JSON object that determines what events will be sent in that subscription.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct ReqFilter {
-    /// a list of event ids or prefixes
-    pub ids: Option<Vec<String>>,
-    /// a list of pubkeys or prefixes, the pubkey of an event must be one of these
-    pub authors: Option<Vec<String>>,
+    /// a list of event ids
+    pub ids: Option<Vec<EventId>>,
+    /// a list of pubkeys, the pubkey of an event must be one of these
+    pub authors: Option<Vec<PublicKey>>,
     /// a list of a kind numbers
-    pub kinds: Option<Vec<u16>>,
-    /// a list of event ids that are referenced in an "e" tag
-    #[serde(rename = "#e")]
-    pub e: Option<Vec<String>>,
-    /// a list of pubkeys that are referenced in a "p" tag
-    #[serde(rename = "#p")]
-    pub p: Option<Vec<String>>,
+    pub kinds: Option<Vec<Kind>>,
+    /// single-letter tag filters, serialized as `#<letter>`: the event must have a tag with
+    /// that name whose value is one of these (e.g. `'e'` for event ids, `'t'` for hashtags)
+    pub tags: BTreeMap<char, Vec<String>>,
     /// a timestamp, events must be newer than this to pass
     pub since: Option<u64>,
     /// a timestamp, events must be older than this to pass
@@ -36,6 +51,161 @@ pub struct ReqFilter {
 }
 
 impl ReqFilter {
+    /// Create an empty filter, matching every event
+    /// # Example
+    /// ```rust
+    /// use rusted_nostr_tools::{keys::Keys, req::ReqFilter};
+    ///
+    /// let filter = ReqFilter::new()
+    ///     .authors(["3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d"])
+    ///     .unwrap()
+    ///     .pubkey(Keys::generate().public_key())
+    ///     .unwrap()
+    ///     .kinds([1, 6])
+    ///     .since(1_700_000_000)
+    ///     .limit(50)
+    ///     .hashtag("Nostr");
+    ///
+    /// assert_eq!(filter.tag('t'), Some(&["nostr".to_string()][..]));
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add event ids, given as [`EventId`]s, hex or `note` bech32
+    pub fn ids<I>(mut self, ids: I) -> Result<Self, ReqFilterError>
+    where
+        I: IntoIterator,
+        I::Item: IntoEventId,
+    {
+        let ids = event_ids(ids)?;
+        self.ids.get_or_insert_with(Vec::new).extend(ids);
+
+        Ok(self)
+    }
+
+    /// Add an event id, given as an [`EventId`], hex or `note` bech32
+    pub fn id(self, id: impl IntoEventId) -> Result<Self, ReqFilterError> {
+        self.ids([id])
+    }
+
+    /// Add authors, given as [`PublicKey`]s, hex or `npub` bech32
+    pub fn authors<I>(mut self, authors: I) -> Result<Self, ReqFilterError>
+    where
+        I: IntoIterator,
+        I::Item: IntoPublicKey,
+    {
+        let authors = public_keys(authors)?;
+        self.authors.get_or_insert_with(Vec::new).extend(authors);
+
+        Ok(self)
+    }
+
+    /// Add an author, given as a [`PublicKey`], hex or `npub` bech32
+    pub fn author(self, author: impl IntoPublicKey) -> Result<Self, ReqFilterError> {
+        self.authors([author])
+    }
+
+    pub fn kinds<I, K>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<Kind>,
+    {
+        let kinds = kinds.into_iter().map(Into::into);
+        self.kinds.get_or_insert_with(Vec::new).extend(kinds);
+        self
+    }
+
+    pub fn kind(self, kind: impl Into<Kind>) -> Self {
+        self.kinds([kind.into()])
+    }
+
+    /// Add event ids referenced in an "e" tag, given as [`EventId`]s, hex or `note` bech32
+    pub fn events<I>(self, ids: I) -> Result<Self, ReqFilterError>
+    where
+        I: IntoIterator,
+        I::Item: IntoEventId,
+    {
+        let ids = event_ids(ids)?;
+        Ok(self.add_tag('e', ids.iter().map(EventId::to_hex)))
+    }
+
+    /// Add an event id referenced in an "e" tag, given as an [`EventId`], hex or `note` bech32
+    pub fn event(self, id: impl IntoEventId) -> Result<Self, ReqFilterError> {
+        self.events([id])
+    }
+
+    /// Add pubkeys referenced in a "p" tag, given as [`PublicKey`]s, hex or `npub` bech32
+    pub fn pubkeys<I>(self, pubkeys: I) -> Result<Self, ReqFilterError>
+    where
+        I: IntoIterator,
+        I::Item: IntoPublicKey,
+    {
+        let pubkeys = public_keys(pubkeys)?;
+        Ok(self.add_tag('p', pubkeys.iter().map(PublicKey::to_hex)))
+    }
+
+    /// Add a pubkey referenced in a "p" tag, given as a [`PublicKey`], hex or `npub` bech32
+    pub fn pubkey(self, pubkey: impl IntoPublicKey) -> Result<Self, ReqFilterError> {
+        self.pubkeys([pubkey])
+    }
+
+    /// Add a hashtag ("t" tag), hashtags are lowercased
+    pub fn hashtag(self, hashtag: &str) -> Self {
+        self.add_tag('t', [hashtag.to_lowercase()])
+    }
+
+    pub fn hashtags<I, S>(self, hashtags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let hashtags = hashtags.into_iter().map(|h| h.as_ref().to_lowercase());
+        self.add_tag('t', hashtags)
+    }
+
+    /// Add the identifier ("d" tag) of an addressable event
+    pub fn identifier(self, identifier: &str) -> Self {
+        self.add_tag('d', [identifier.to_string()])
+    }
+
+    /// Add values of any single-letter tag, "e" and "p" values are parsed like in
+    /// [`ReqFilter::events`] and [`ReqFilter::pubkeys`]
+    pub fn custom_tag<I, S>(self, letter: char, values: I) -> Result<Self, ReqFilterError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let values = values.into_iter().map(|v| v.as_ref().to_string());
+
+        match letter {
+            'e' => self.events(values),
+            'p' => self.pubkeys(values),
+            letter if letter.is_ascii_alphabetic() => Ok(self.add_tag(letter, values)),
+            letter => Err(ReqFilterError::InvalidTagName(letter)),
+        }
+    }
+
+    pub fn since(mut self, since: u64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn until(mut self, until: u64) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn add_tag(mut self, letter: char, values: impl IntoIterator<Item = String>) -> Self {
+        self.tags.entry(letter).or_default().extend(values);
+        self
+    }
+
     /// Return a clean json object (Value)
     pub fn to_json(&self) -> serde_json::Value {
         let mut json = json!({});
@@ -52,12 +222,8 @@ impl ReqFilter {
             json["kinds"] = json!(kinds);
         }
 
-        if let Some(e) = &self.e {
-            json["#e"] = json!(e);
-        }
-
-        if let Some(p) = &self.p {
-            json["#p"] = json!(p);
+        for (letter, values) in &self.tags {
+            json[format!("#{}", letter)] = json!(values);
         }
 
         if let Some(since) = &self.since {
@@ -74,6 +240,136 @@ impl ReqFilter {
 
         json
     }
+
+    /// Return the values of a single-letter tag filter
+    pub fn tag(&self, letter: char) -> Option<&[String]> {
+        self.tags.get(&letter).map(Vec::as_slice)
+    }
+
+    /// Return the event ids that are referenced in an "e" tag, empty if there is no "e" filter
+    pub fn e(&self) -> Result<Vec<EventId>, ReqFilterError> {
+        event_ids(self.tag('e').unwrap_or_default())
+    }
+
+    /// Return the pubkeys that are referenced in a "p" tag, empty if there is no "p" filter
+    pub fn p(&self) -> Result<Vec<PublicKey>, ReqFilterError> {
+        public_keys(self.tag('p').unwrap_or_default())
+    }
+
+    /// Check if an event matches the filter, every condition set in the filter must be satisfied
+    /// (`limit` is ignored)
+    pub fn matches(&self, event: &SignedEvent) -> bool {
+        let has_tag = |letter: &char, values: &Vec<String>| {
+            event.tags.iter().any(|tag| {
+                tag.len() > 1
+                    && tag[0].len() == 1
+                    && tag[0].starts_with(*letter)
+                    && values.contains(&tag[1])
+            })
+        };
+
+        self.ids.as_ref().is_none_or(|ids| ids.contains(&event.id))
+            && self
+                .authors
+                .as_ref()
+                .is_none_or(|authors| authors.contains(&event.pubkey))
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&event.kind))
+            && self
+                .tags
+                .iter()
+                .all(|(letter, values)| has_tag(letter, values))
+            && self
+                .since
+                .is_none_or(|since| event.created_at >= since as i64)
+            && self
+                .until
+                .is_none_or(|until| event.created_at <= until as i64)
+    }
+
+    /// Check if an event matches any of the filters
+    pub fn matches_any(filters: &[ReqFilter], event: &SignedEvent) -> bool {
+        filters.iter().any(|filter| filter.matches(event))
+    }
+}
+
+/// Convert ids given as [`EventId`]s, hex or `note` bech32, returns [`ReqFilterError::Parse`]
+/// for the first invalid one
+fn event_ids<I>(ids: I) -> Result<Vec<EventId>, ReqFilterError>
+where
+    I: IntoIterator,
+    I::Item: IntoEventId,
+{
+    let ids = ids.into_iter().map(IntoEventId::into_event_id);
+
+    Ok(ids.collect::<Result<_, _>>()?)
+}
+
+/// Convert pubkeys given as [`PublicKey`]s, hex or `npub` bech32, returns
+/// [`ReqFilterError::Parse`] for the first invalid one
+fn public_keys<I>(pubkeys: I) -> Result<Vec<PublicKey>, ReqFilterError>
+where
+    I: IntoIterator,
+    I::Item: IntoPublicKey,
+{
+    let pubkeys = pubkeys.into_iter().map(IntoPublicKey::into_public_key);
+
+    Ok(pubkeys.collect::<Result<_, _>>()?)
+}
+
+impl Serialize for ReqFilter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReqFilter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = Map::<String, Value>::deserialize(deserializer)?;
+        let mut filter = ReqFilter::default();
+
+        for (key, value) in map {
+            if value.is_null() {
+                continue;
+            }
+
+            match key.as_str() {
+                "ids" => filter.ids = serde_json::from_value(value).map_err(D::Error::custom)?,
+                "authors" => {
+                    filter.authors = serde_json::from_value(value).map_err(D::Error::custom)?
+                }
+                "kinds" => {
+                    filter.kinds = serde_json::from_value(value).map_err(D::Error::custom)?
+                }
+                "since" => {
+                    filter.since = serde_json::from_value(value).map_err(D::Error::custom)?
+                }
+                "until" => {
+                    filter.until = serde_json::from_value(value).map_err(D::Error::custom)?
+                }
+                "limit" => {
+                    filter.limit = serde_json::from_value(value).map_err(D::Error::custom)?
+                }
+                _ => {
+                    let mut chars = key.chars();
+
+                    // Only single-letter tags can be queried, other keys are ignored
+                    if let (Some('#'), Some(letter), None) =
+                        (chars.next(), chars.next(), chars.next())
+                    {
+                        if letter.is_ascii_alphabetic() {
+                            let values = serde_json::from_value(value).map_err(D::Error::custom)?;
+                            filter.tags.insert(letter, values);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(filter)
+    }
 }
 
 impl Req {
@@ -84,23 +380,27 @@ impl Req {
         }
     }
 
+    /// Return the serialized `CLOSE` message ending this subscription
     pub fn get_close_event(&self) -> String {
-        json!({
-            "subscription_id": self.subscription_id,
-            "close": true
-        })
-        .to_string()
+        ClientMessage::Close {
+            subscription_id: self.subscription_id.clone(),
+        }
+        .to_json()
+    }
+}
+
+impl From<Req> for ClientMessage {
+    fn from(req: Req) -> Self {
+        ClientMessage::Req {
+            subscription_id: req.subscription_id,
+            filters: req.filters,
+        }
     }
 }
 
 impl fmt::Display for Req {
     /// Return the serialized event
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut req = json!(["REQ", self.subscription_id]);
-        for filter in &self.filters {
-            req.as_array_mut().unwrap().push(filter.to_json());
-        }
-
-        write!(f, "{}", serde_json::to_string(&req).unwrap())
+        write!(f, "{}", ClientMessage::from(self.clone()).to_json())
     }
 }
@@ -0,0 +1,261 @@
+use futures::StreamExt;
+use futures_util::sink::SinkExt;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+use super::{client_message::ClientMessage, relay_message::RelayMessage, req::ReqFilter};
+use crate::functions::event_methods::{SignedEvent, VerifyError};
+
+/// Capacity of the channel pushing new events to the open subscriptions
+const LIVE_CHANNEL_SIZE: usize = 1024;
+
+/// State shared by every connection of the relay
+struct State {
+    events: Mutex<Vec<SignedEvent>>,
+    live: broadcast::Sender<SignedEvent>,
+}
+
+/// TestRelay is a small NIP-01 relay listening on localhost and keeping its events in memory.
+///
+/// It answers `REQ` with the stored events followed by `EOSE`, accepts `EVENT` after checking the id
+/// and signature, honours `CLOSE` and pushes new events to the matching subscriptions. It is meant
+/// for offline integration tests, replaceable events and authentication are not implemented.
+/// # Example
+/// ```rust
+/// use rusted_nostr_tools::{client::Client, test_relay::TestRelay};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let relay = TestRelay::run().await.unwrap();
+///     let client = Client::new(vec![&relay.url()]).await.unwrap();
+///
+///     relay.shutdown().await;
+/// }
+/// ```
+pub struct TestRelay {
+    addr: SocketAddr,
+    state: Arc<State>,
+    shutdown: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl TestRelay {
+    /// Start a relay on a random port of 127.0.0.1
+    pub async fn run() -> std::io::Result<Self> {
+        Self::bind("127.0.0.1:0").await
+    }
+
+    /// Start a relay on the given address, e.g. to restart a relay on the same port
+    pub async fn bind(addr: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+
+        let (live, _) = broadcast::channel(LIVE_CHANNEL_SIZE);
+        let state = Arc::new(State {
+            events: Mutex::new(Vec::new()),
+            live,
+        });
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+
+        let task = tokio::spawn(accept(listener, state.clone(), shutdown_receiver));
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown,
+            task,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Websocket url of the relay
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Events stored by the relay
+    pub fn events(&self) -> Vec<SignedEvent> {
+        self.state.events.lock().unwrap().clone()
+    }
+
+    /// Stop listening and wait for every open connection to be closed
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(true);
+        let _ = self.task.await;
+    }
+}
+
+async fn accept(listener: TcpListener, state: Arc<State>, mut shutdown: watch::Receiver<bool>) {
+    let mut connections = Vec::new();
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            connection = listener.accept() => {
+                if let Ok((stream, _)) = connection {
+                    connections.push(tokio::spawn(serve(stream, state.clone(), shutdown.clone())));
+                }
+            }
+        }
+    }
+
+    for connection in connections {
+        let _ = connection.await;
+    }
+}
+
+async fn serve(stream: TcpStream, state: Arc<State>, mut shutdown: watch::Receiver<bool>) {
+    let mut socket = match accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(_) => return,
+    };
+
+    let mut live = state.live.subscribe();
+    let mut subscriptions: HashMap<String, Vec<ReqFilter>> = HashMap::new();
+
+    loop {
+        let responses = tokio::select! {
+            _ = shutdown.changed() => {
+                let _ = socket.close(None).await;
+                return;
+            }
+            incoming = socket.next() => match incoming {
+                Some(Ok(Message::Text(text))) => handle(&text, &state, &mut subscriptions),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => continue,
+            },
+            event = live.recv() => match event {
+                Ok(event) => subscriptions
+                    .iter()
+                    .filter(|(_, filters)| ReqFilter::matches_any(filters, &event))
+                    .map(|(subscription_id, _)| RelayMessage::Event {
+                        subscription_id: subscription_id.to_string(),
+                        event: Box::new(event.clone()),
+                    })
+                    .collect(),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+
+        for response in responses {
+            if socket.send(response.to_message()).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Handle a message sent by a client and return the responses
+fn handle(
+    text: &str,
+    state: &State,
+    subscriptions: &mut HashMap<String, Vec<ReqFilter>>,
+) -> Vec<RelayMessage> {
+    let message = match ClientMessage::from_json(text) {
+        Ok(message) => message,
+        Err(err) => {
+            return vec![RelayMessage::Notice {
+                message: format!("error: {}", err),
+            }]
+        }
+    };
+
+    match message {
+        ClientMessage::Event(event) => vec![store(*event, state)],
+        ClientMessage::Req {
+            subscription_id,
+            filters,
+        } => {
+            let mut responses: Vec<RelayMessage> = query(&filters, state)
+                .into_iter()
+                .map(|event| RelayMessage::Event {
+                    subscription_id: subscription_id.to_string(),
+                    event: Box::new(event),
+                })
+                .collect();
+            responses.push(RelayMessage::Eose {
+                subscription_id: subscription_id.to_string(),
+            });
+
+            subscriptions.insert(subscription_id, filters);
+            responses
+        }
+        ClientMessage::Close { subscription_id } => {
+            subscriptions.remove(&subscription_id);
+            vec![]
+        }
+        ClientMessage::Count {
+            subscription_id,
+            filters,
+        } => vec![RelayMessage::Count {
+            subscription_id,
+            count: query(&filters, state).len() as u64,
+        }],
+        _ => vec![RelayMessage::Notice {
+            message: "error: unsupported message".to_string(),
+        }],
+    }
+}
+
+/// Check and store an event, returns the OK message to send back
+fn store(event: SignedEvent, state: &State) -> RelayMessage {
+    let ok = |status: bool, message: &str| RelayMessage::Ok {
+        event_id: event.id,
+        status,
+        message: message.to_string(),
+    };
+
+    match event.verify() {
+        Ok(()) => {}
+        Err(VerifyError::InvalidSignature) => {
+            return ok(false, "invalid: signature verification failed")
+        }
+        Err(_) => return ok(false, "invalid: event id does not match"),
+    }
+
+    {
+        let mut events = state.events.lock().unwrap();
+        if events.iter().any(|stored| stored.id == event.id) {
+            return ok(true, "duplicate: already have this event");
+        }
+
+        // Ephemeral events are only pushed to the open subscriptions
+        if !event.kind.is_ephemeral() {
+            events.push(event.clone());
+        }
+    }
+
+    let response = ok(true, "");
+    let _ = state.live.send(event);
+
+    response
+}
+
+/// Return the stored events matching any of the filters, newest first
+fn query(filters: &[ReqFilter], state: &State) -> Vec<SignedEvent> {
+    let mut events = state.events.lock().unwrap().clone();
+    events.sort_by_key(|event| std::cmp::Reverse(event.created_at));
+
+    let mut results: Vec<SignedEvent> = Vec::new();
+    for filter in filters {
+        let matching = events.iter().filter(|event| filter.matches(event));
+
+        for event in matching.take(filter.limit.unwrap_or(u64::MAX) as usize) {
+            if !results.contains(event) {
+                results.push(event.clone());
+            }
+        }
+    }
+
+    results.sort_by_key(|event| std::cmp::Reverse(event.created_at));
+    results
+}
@@ -1,33 +1,136 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher},
+    ChaCha20,
+};
 use chrono::Utc;
+use hmac::{Hmac, Mac};
 use rusted_nostr_tools::{
+    client::{Client, ClientError, PublishOptions, PublishStatus},
+    client_message::{ClientMessage, ClientMessageError},
+    event_builder::{Contact, EventBuilder},
     event_methods::{
-        get_event_hash, serialize_event, sign_event, validate_event, verify_signature,
-        UnsignedEvent,
+        get_event_hash, serialize_event, sign_event, validate_event, verify_events,
+        verify_signature, SignedEvent, UnsignedEvent, VerifyError,
     },
-    ConvertKey, GeneratePrivateKey, GeneratePublicKey, Nip05Query,
+    keys::Keys,
+    kind::Kind,
+    nip04::{self, Nip04Error},
+    nip06::{self, Nip06Error},
+    nip19::{Nip19, Nip19Address, Nip19Error, Nip19Event, Nip19Profile, Nip19Relay},
+    nip21::{Nip21Error, NostrUri},
+    nip44::{self, ConversationKey, Nip44Error},
+    nip59::{self, Nip59Error, UnwrappedGift},
+    relay::{Notification, ReconnectOptions, RelayStatus},
+    relay_message::{MachineReadablePrefix, RelayMessage, RelayMessageError},
+    relay_pool::RelayOptions,
+    req::{Req, ReqFilter, ReqFilterError},
+    signer::NostrSigner,
+    test_relay::TestRelay,
+    types::{EventId, ParseError, PublicKey, SecretKey, Signature},
+    ConvertKey, Error, GeneratePrivateKey, GeneratePublicKey, Nip05Query,
 };
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn text_note(key: &Keys, content: &str, created_at: i64) -> SignedEvent {
+    EventBuilder::text_note(content)
+        .custom_created_at(created_at)
+        .build(key)
+        .unwrap()
+}
+
+fn authors_filter(key: &Keys) -> ReqFilter {
+    let pubkey = GeneratePublicKey::new(key.secret_key());
+
+    ReqFilter {
+        authors: Some(vec![*pubkey.public_key()]),
+        kinds: Some(vec![Kind::TextNote]),
+        ..Default::default()
+    }
+}
+
+/// Wait until the relay status matches the predicate
+async fn wait_for_status(
+    status: &mut tokio::sync::watch::Receiver<RelayStatus>,
+    predicate: impl Fn(RelayStatus) -> bool,
+) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !predicate(*status.borrow()) {
+            status.changed().await.unwrap();
+        }
+    })
+    .await
+    .expect("timed out waiting for the relay status")
+}
+
+/// Wait for the end of the stored events of a subscription
+async fn wait_for_eose(
+    notifications: &mut tokio::sync::broadcast::Receiver<Notification>,
+    id: &str,
+) {
+    loop {
+        let (_, message) = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .expect("timed out waiting for EOSE")
+            .unwrap();
+
+        if message
+            == Ok(RelayMessage::Eose {
+                subscription_id: id.to_string(),
+            })
+        {
+            return;
+        }
+    }
+}
+
+/// Wait for the next event of a subscription
+async fn next_event(
+    notifications: &mut tokio::sync::broadcast::Receiver<Notification>,
+    id: &str,
+) -> SignedEvent {
+    loop {
+        let (_, message) = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .expect("timed out waiting for an event")
+            .unwrap();
+
+        if let Ok(RelayMessage::Event {
+            subscription_id,
+            event,
+        }) = message
+        {
+            if subscription_id == id {
+                return *event;
+            }
+        }
+    }
+}
 
 #[test]
 fn test_generate_private_key() {
     let key = GeneratePrivateKey::new();
     assert_eq!(key.hex_private_key().len(), 64);
-    assert_eq!(key.bech32_private_key().is_empty(), false);
+    assert!(!key.bech32_private_key().unwrap().is_empty());
 }
 
 #[test]
 fn test_generate_public_key() {
     let key = GeneratePrivateKey::new();
-    let pubkey = GeneratePublicKey::new(key.hex_private_key());
+    let pubkey = GeneratePublicKey::new(key.secret_key());
     assert_eq!(pubkey.hex_public_key().len(), 64);
-    assert_eq!(pubkey.bech32_public_key().is_empty(), false);
+    assert!(!pubkey.bech32_public_key().unwrap().is_empty());
 }
 
 #[test]
 fn bech32_key_to_hex() {
     let key = GeneratePrivateKey::new();
-    let pubkey = GeneratePublicKey::new(key.hex_private_key());
-    let hex_pubkey = ConvertKey::to_hex(pubkey.bech32_public_key());
-    let hex_privkey = ConvertKey::to_hex(key.bech32_private_key());
+    let pubkey = GeneratePublicKey::new(key.secret_key());
+    let hex_pubkey = ConvertKey::to_hex(&pubkey.bech32_public_key().unwrap());
+    let hex_privkey = ConvertKey::to_hex(&key.bech32_private_key().unwrap());
     assert!(hex_pubkey.is_ok());
     assert!(hex_privkey.is_ok());
     assert_eq!(hex_pubkey.unwrap(), pubkey.hex_public_key());
@@ -37,45 +140,45 @@ fn bech32_key_to_hex() {
 #[test]
 fn hex_key_to_bech32_public_key() {
     let key = GeneratePrivateKey::new();
-    let pubkey = GeneratePublicKey::new(key.hex_private_key());
-    let bech32_pubkey = ConvertKey::to_bech32_public_key(pubkey.hex_public_key());
-    assert_eq!(bech32_pubkey, pubkey.bech32_public_key());
+    let pubkey = GeneratePublicKey::new(key.secret_key());
+    let bech32_pubkey = ConvertKey::to_bech32_public_key(pubkey.public_key()).unwrap();
+    assert_eq!(bech32_pubkey, pubkey.bech32_public_key().unwrap());
 }
 
 #[test]
 fn hex_key_to_bech32_private_key() {
     let key = GeneratePrivateKey::new();
-    let bech32_privkey = ConvertKey::to_bech32_private_key(key.hex_private_key());
-    assert_eq!(bech32_privkey, key.bech32_private_key());
+    let bech32_privkey = ConvertKey::to_bech32_private_key(key.secret_key()).unwrap();
+    assert_eq!(bech32_privkey, key.bech32_private_key().unwrap());
 }
 
 #[tokio::test]
 async fn nip05_query() {
     let domain = "noderunner.wtf";
     let nip05 = Nip05Query::new(domain).await;
-    assert_eq!(nip05.is_ok(), true);
+    assert!(nip05.is_ok());
     let nip05_2 = Nip05Query::new(domain).await.unwrap();
     assert!(nip05_2.query().names.contains_key("nitesh"));
 }
 
 #[test]
 fn signature() {
-    let key = GeneratePrivateKey::new();
-    let binding = GeneratePublicKey::new(key.hex_private_key());
-    let pubkey = binding.hex_public_key();
+    let key = Keys::generate();
+    let binding = GeneratePublicKey::new(key.secret_key());
+    let pubkey = binding.public_key();
 
     let content = "yo".to_string();
 
     let event = UnsignedEvent {
-        pubkey: pubkey.to_string(),
+        pubkey: *pubkey,
         created_at: Utc::now().timestamp(),
-        kind: 0,
+        kind: Kind::Metadata,
         tags: vec![],
         content,
     };
 
     let is_valid = validate_event(&event);
-    assert_eq!(is_valid, true);
+    assert!(is_valid);
 
     let serialized_event = serialize_event(&event);
     assert!(serialized_event.is_ok());
@@ -83,9 +186,2273 @@ fn signature() {
     let hash = get_event_hash(&event);
     assert!(&hash.is_ok());
 
-    let signature = sign_event(&event, key.hex_private_key());
+    let signature = sign_event(&event, &key);
     assert!(&signature.is_ok());
 
     let is_verified = verify_signature(&signature.unwrap().sig, pubkey, &hash.unwrap());
-    assert_eq!(is_verified.is_ok(), true);
+    assert!(is_verified.is_ok());
+}
+
+#[test]
+fn parse_relay_messages() {
+    let key = Keys::generate();
+    let binding = GeneratePublicKey::new(key.secret_key());
+
+    let event = UnsignedEvent {
+        pubkey: *binding.public_key(),
+        created_at: Utc::now().timestamp(),
+        kind: Kind::TextNote,
+        tags: vec![],
+        content: "hello".to_string(),
+    };
+    let event = sign_event(&event, &key).unwrap();
+
+    let json = serde_json::json!(["EVENT", "sub", event]).to_string();
+    assert_eq!(
+        RelayMessage::from_json(&json),
+        Ok(RelayMessage::Event {
+            subscription_id: "sub".to_string(),
+            event: Box::new(event.clone()),
+        })
+    );
+
+    let json = serde_json::json!(["OK", event.id, false, "blocked: nope"]).to_string();
+    assert_eq!(
+        RelayMessage::from_json(&json),
+        Ok(RelayMessage::Ok {
+            event_id: event.id,
+            status: false,
+            message: "blocked: nope".to_string(),
+        })
+    );
+
+    assert_eq!(
+        RelayMessage::from_json(r#"["EOSE","sub"]"#),
+        Ok(RelayMessage::Eose {
+            subscription_id: "sub".to_string()
+        })
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["NOTICE","hi"]"#),
+        Ok(RelayMessage::Notice {
+            message: "hi".to_string()
+        })
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["CLOSED","sub","error: shutting down"]"#),
+        Ok(RelayMessage::Closed {
+            subscription_id: "sub".to_string(),
+            message: "error: shutting down".to_string(),
+        })
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["AUTH","challenge"]"#),
+        Ok(RelayMessage::Auth {
+            challenge: "challenge".to_string()
+        })
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["COUNT","sub",{"count":42}]"#),
+        Ok(RelayMessage::Count {
+            subscription_id: "sub".to_string(),
+            count: 42,
+        })
+    );
+}
+
+#[test]
+fn parse_invalid_relay_messages() {
+    assert_eq!(
+        RelayMessage::from_json("not json"),
+        Err(RelayMessageError::InvalidJson)
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"{"type":"EOSE"}"#),
+        Err(RelayMessageError::InvalidJson)
+    );
+    assert_eq!(
+        RelayMessage::from_json("[]"),
+        Err(RelayMessageError::MissingType)
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["PING"]"#),
+        Err(RelayMessageError::UnknownType("PING".to_string()))
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["EVENT","sub",{"content":"missing fields"}]"#),
+        Err(RelayMessageError::Malformed("EVENT"))
+    );
+    assert_eq!(
+        RelayMessage::from_json(r#"["OK","id","true",""]"#),
+        Err(RelayMessageError::Malformed("OK"))
+    );
+    assert_eq!(
+        RelayMessage::from_message(&tungstenite::Message::Ping(vec![])),
+        Err(RelayMessageError::NonTextFrame)
+    );
+}
+
+#[test]
+fn client_messages_round_trip() {
+    let key = Keys::generate();
+    let binding = GeneratePublicKey::new(key.secret_key());
+
+    let event = UnsignedEvent {
+        pubkey: *binding.public_key(),
+        created_at: Utc::now().timestamp(),
+        kind: Kind::TextNote,
+        tags: vec![vec!["t".to_string(), "nostr".to_string()]],
+        content: "hello".to_string(),
+    };
+    let event = sign_event(&event, &key).unwrap();
+
+    let filter = ReqFilter {
+        authors: Some(vec![*binding.public_key()]),
+        kinds: Some(vec![Kind::TextNote, Kind::Repost]),
+        since: Some(1_700_000_000),
+        limit: Some(10),
+        ..Default::default()
+    };
+
+    let messages = vec![
+        ClientMessage::Event(Box::new(event.clone())),
+        ClientMessage::Req {
+            subscription_id: "sub".to_string(),
+            filters: vec![filter.clone(), filter.clone()],
+        },
+        ClientMessage::Close {
+            subscription_id: "sub".to_string(),
+        },
+        ClientMessage::Auth(Box::new(event)),
+        ClientMessage::Count {
+            subscription_id: "sub".to_string(),
+            filters: vec![filter.clone()],
+        },
+        ClientMessage::NegOpen {
+            subscription_id: "neg".to_string(),
+            filter: Box::new(filter),
+            initial_message: "6100".to_string(),
+        },
+        ClientMessage::NegMsg {
+            subscription_id: "neg".to_string(),
+            message: "6101".to_string(),
+        },
+        ClientMessage::NegClose {
+            subscription_id: "neg".to_string(),
+        },
+    ];
+
+    for message in messages {
+        assert_eq!(ClientMessage::from_json(&message.to_json()), Ok(message));
+    }
+}
+
+#[test]
+fn serialize_req_messages() {
+    let req = Req::new(
+        Some("sub"),
+        vec![ReqFilter {
+            kinds: Some(vec![Kind::Metadata]),
+            limit: Some(1),
+            ..Default::default()
+        }],
+    );
+
+    assert_eq!(req.to_string(), r#"["REQ","sub",{"kinds":[0],"limit":1}]"#);
+    assert_eq!(req.get_close_event(), r#"["CLOSE","sub"]"#);
+    assert_eq!(
+        ClientMessage::from_json(r#"["CLOSE"]"#),
+        Err(ClientMessageError::Malformed("CLOSE"))
+    );
+    assert_eq!(
+        ClientMessage::from_json(r#"["HELLO","sub"]"#),
+        Err(ClientMessageError::UnknownType("HELLO".to_string()))
+    );
+}
+
+#[test]
+fn parse_machine_readable_prefixes() {
+    assert_eq!(
+        MachineReadablePrefix::parse("blocked: you are banned"),
+        Some(MachineReadablePrefix::Blocked)
+    );
+    assert_eq!(
+        MachineReadablePrefix::parse("rate-limited: slow down"),
+        Some(MachineReadablePrefix::RateLimited)
+    );
+    assert_eq!(
+        MachineReadablePrefix::parse("duplicate: already have this event"),
+        Some(MachineReadablePrefix::Duplicate)
+    );
+    assert_eq!(
+        MachineReadablePrefix::parse("pow: difficulty 20 required"),
+        Some(MachineReadablePrefix::Pow)
+    );
+    assert_eq!(MachineReadablePrefix::parse(""), None);
+    assert_eq!(MachineReadablePrefix::parse("unknown: prefix"), None);
+    assert_eq!(MachineReadablePrefix::Invalid.to_string(), "invalid");
+}
+
+#[tokio::test]
+async fn client_publish_event_with_ack() {
+    let relay = TestRelay::run().await.unwrap();
+    let mut client = Client::new(vec![&relay.url()]).await.unwrap();
+
+    let key = Keys::generate();
+    let event = text_note(&key, "hello", Utc::now().timestamp());
+
+    let statuses = client
+        .publish_event_with_ack(&event, PublishOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        statuses[&relay.url()],
+        PublishStatus::Accepted {
+            prefix: None,
+            message: "".to_string()
+        }
+    );
+
+    let statuses = client
+        .publish_event_with_ack(&event, PublishOptions::default())
+        .await
+        .unwrap();
+    assert!(matches!(
+        &statuses[&relay.url()],
+        PublishStatus::Accepted {
+            prefix: Some(MachineReadablePrefix::Duplicate),
+            ..
+        }
+    ));
+
+    let mut forged = text_note(&key, "hello again", Utc::now().timestamp());
+    forged.content = "forged".to_string();
+
+    let result = client
+        .publish_event_with_ack(
+            &forged,
+            PublishOptions {
+                quorum: Some(1),
+                ..Default::default()
+            },
+        )
+        .await;
+    match result {
+        Err(ClientError::QuorumNotReached {
+            required,
+            accepted,
+            statuses,
+        }) => {
+            assert_eq!((required, accepted), (1, 0));
+            assert!(matches!(
+                &statuses[&relay.url()],
+                PublishStatus::Rejected {
+                    prefix: Some(MachineReadablePrefix::Invalid),
+                    ..
+                }
+            ));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // A quorum of 0 is met without waiting for any answer
+    let statuses = client
+        .publish_event_with_ack(
+            &forged,
+            PublishOptions {
+                quorum: Some(0),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(statuses[&relay.url()], PublishStatus::Pending);
+
+    assert_eq!(relay.events(), vec![event]);
+    relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_publish_event_with_ack_terminated_relays() {
+    use futures::StreamExt;
+
+    // Relay dropping the connection once it received the event, without answering
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let dropping = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        socket.next().await;
+    });
+    let dead_relay = TestRelay::run().await.unwrap();
+    let dead_url = dead_relay.url();
+
+    let mut client = Client::new(vec![]).await.unwrap();
+    client.reconnect_options = ReconnectOptions {
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+        max_attempts: Some(0),
+        ..Default::default()
+    };
+    client.add_relay(&dropping).await.unwrap();
+    client.add_relay(&dead_url).await.unwrap();
+
+    let mut status = client.pool.relay(&dead_url).unwrap().status_watch();
+    dead_relay.shutdown().await;
+    wait_for_status(&mut status, |status| status == RelayStatus::Terminated).await;
+
+    let event = text_note(&Keys::generate(), "terminated", Utc::now().timestamp());
+    let statuses = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.publish_event_with_ack(
+            &event,
+            PublishOptions {
+                timeout: Duration::from_secs(30),
+                ..Default::default()
+            },
+        ),
+    )
+    .await
+    .expect("terminated relays should not be waited for")
+    .unwrap();
+
+    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses[&dropping], PublishStatus::Terminated);
+    assert_eq!(statuses[&dead_url], PublishStatus::Terminated);
+}
+
+#[tokio::test]
+async fn client_get_events_of() {
+    let relay = TestRelay::run().await.unwrap();
+    let mut client = Client::new(vec![&relay.url()]).await.unwrap();
+
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let now = Utc::now().timestamp();
+
+    for event in [
+        text_note(&alice, "one", now - 2),
+        text_note(&alice, "two", now - 1),
+        text_note(&bob, "three", now),
+    ] {
+        client
+            .publish_event_with_ack(&event, PublishOptions::default())
+            .await
+            .unwrap();
+    }
+
+    let events = client
+        .get_events_of(vec![authors_filter(&alice)])
+        .await
+        .unwrap();
+    let contents: Vec<&str> = events.iter().map(|e| e.content.as_str()).collect();
+    assert_eq!(contents, vec!["two", "one"]);
+
+    relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_live_subscription() {
+    let relay = TestRelay::run().await.unwrap();
+    let mut reader = Client::new(vec![&relay.url()]).await.unwrap();
+    let mut writer = Client::new(vec![&relay.url()]).await.unwrap();
+
+    let key = Keys::generate();
+    let mut notifications = reader.notifications();
+    let id = reader.subscribe(vec![authors_filter(&key)]).await.unwrap();
+    wait_for_eose(&mut notifications, &id).await;
+
+    let event = text_note(&key, "live", Utc::now().timestamp());
+    writer
+        .publish_event_with_ack(&event, PublishOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(next_event(&mut notifications, &id).await, event);
+
+    let data = reader.next_data().await.unwrap();
+    assert!(data
+        .iter()
+        .any(|(relay_url, message)| relay_url == &relay.url()
+            && message
+                .as_ref()
+                .is_ok_and(|message| message.subscription_id() == Some(id.as_str()))));
+
+    // No more events once unsubscribed
+    reader.unsubscribe(&id).await.unwrap();
+    // The relay handles the messages of a connection in order, so CLOSE is done once this returns
+    reader
+        .get_events_of(vec![authors_filter(&key)])
+        .await
+        .unwrap();
+    let event = text_note(&key, "too late", Utc::now().timestamp());
+    writer
+        .publish_event_with_ack(&event, PublishOptions::default())
+        .await
+        .unwrap();
+    let received = tokio::time::timeout(
+        Duration::from_millis(300),
+        next_event(&mut notifications, &id),
+    )
+    .await;
+    assert!(received.is_err());
+
+    relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_reconnects_and_replays_subscriptions() {
+    let relay = TestRelay::run().await.unwrap();
+    let addr = relay.addr().to_string();
+    let url = relay.url();
+
+    let mut client = Client::new(vec![]).await.unwrap();
+    client.reconnect_options = ReconnectOptions {
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(200),
+        max_attempts: None,
+        ..Default::default()
+    };
+    client.add_relay(&url).await.unwrap();
+
+    let key = Keys::generate();
+    let mut notifications = client.notifications();
+    let id = client.subscribe(vec![authors_filter(&key)]).await.unwrap();
+    wait_for_eose(&mut notifications, &id).await;
+
+    let mut status = client.pool.relay(&url).unwrap().status_watch();
+    relay.shutdown().await;
+    wait_for_status(&mut status, |status| status != RelayStatus::Connected).await;
+
+    let relay = TestRelay::bind(&addr).await.unwrap();
+    wait_for_status(&mut status, |status| status == RelayStatus::Connected).await;
+    // The subscription was sent again
+    wait_for_eose(&mut notifications, &id).await;
+
+    let mut writer = Client::new(vec![&url]).await.unwrap();
+    let event = text_note(&key, "after restart", Utc::now().timestamp());
+    writer
+        .publish_event_with_ack(&event, PublishOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(next_event(&mut notifications, &id).await, event);
+
+    relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_publishes_events_sent_while_disconnected() {
+    let relay = TestRelay::run().await.unwrap();
+    let addr = relay.addr().to_string();
+    let url = relay.url();
+
+    let mut client = Client::new(vec![]).await.unwrap();
+    client.reconnect_options = ReconnectOptions {
+        initial_delay: Duration::from_millis(50),
+        max_delay: Duration::from_millis(200),
+        max_attempts: None,
+        ..Default::default()
+    };
+    client.add_relay(&url).await.unwrap();
+
+    let key = Keys::generate();
+    let mut status = client.pool.relay(&url).unwrap().status_watch();
+    relay.shutdown().await;
+
+    // Sent before the connection is known to be closed
+    let first = text_note(&key, "while closing", Utc::now().timestamp());
+    client.publish_event(&first).await.unwrap();
+    wait_for_status(&mut status, |status| status != RelayStatus::Connected).await;
+
+    // Sent while reconnecting, the message is queued
+    let second = text_note(&key, "while reconnecting", Utc::now().timestamp());
+    client.publish_event(&second).await.unwrap();
+
+    let relay = TestRelay::bind(&addr).await.unwrap();
+    wait_for_status(&mut status, |status| status == RelayStatus::Connected).await;
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while relay.events().len() < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(relay.events(), vec![first, second]);
+
+    relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn client_drops_the_oldest_messages_queued_while_disconnected() {
+    let relay = TestRelay::run().await.unwrap();
+    let addr = relay.addr().to_string();
+    let url = relay.url();
+
+    let mut client = Client::new(vec![]).await.unwrap();
+    client.reconnect_options = ReconnectOptions {
+        initial_delay: Duration::from_millis(200),
+        max_delay: Duration::from_millis(200),
+        max_attempts: None,
+        max_pending: 1,
+    };
+    client.add_relay(&url).await.unwrap();
+
+    let mut status = client.pool.relay(&url).unwrap().status_watch();
+    relay.shutdown().await;
+    wait_for_status(&mut status, |status| status == RelayStatus::Connecting).await;
+
+    let key = Keys::generate();
+    let first = text_note(&key, "dropped", Utc::now().timestamp());
+    let second = text_note(&key, "kept", Utc::now().timestamp());
+    client.publish_event(&first).await.unwrap();
+    client.publish_event(&second).await.unwrap();
+
+    let relay = TestRelay::bind(&addr).await.unwrap();
+    wait_for_status(&mut status, |status| status == RelayStatus::Connected).await;
+
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while relay.events().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    // Give the dropped message a chance to show up
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(relay.events(), vec![second]);
+
+    relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn relay_pool_roles() {
+    let write_relay = TestRelay::run().await.unwrap();
+    let read_relay = TestRelay::run().await.unwrap();
+
+    let mut client = Client::new(vec![&write_relay.url()]).await.unwrap();
+    client
+        .add_relay_with_options(&read_relay.url(), RelayOptions::read_only())
+        .await
+        .unwrap();
+
+    let mut read_relays = client.pool.read_relays();
+    read_relays.sort();
+    let mut expected = vec![write_relay.url(), read_relay.url()];
+    expected.sort();
+    assert_eq!(read_relays, expected);
+    assert_eq!(client.pool.write_relays(), vec![write_relay.url()]);
+
+    let key = Keys::generate();
+    let event = text_note(&key, "write", Utc::now().timestamp());
+    let statuses = client
+        .publish_event_with_ack(&event, PublishOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(statuses.len(), 1);
+    assert!(statuses[&write_relay.url()].is_accepted());
+    assert_eq!(write_relay.events(), vec![event]);
+    assert!(read_relay.events().is_empty());
+
+    // Explicit relays override the roles
+    let event = text_note(&key, "explicit", Utc::now().timestamp());
+    let statuses = client
+        .publish_event_with_ack(
+            &event,
+            PublishOptions {
+                relays: Some(vec![read_relay.url()]),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(statuses[&read_relay.url()].is_accepted());
+    assert_eq!(read_relay.events(), vec![event]);
+
+    assert!(matches!(
+        client
+            .publish_event_to(&["ws://127.0.0.1:1"], &text_note(&key, "nope", 0))
+            .await,
+        Err(ClientError::RelayDoesNotExist)
+    ));
+
+    write_relay.shutdown().await;
+    read_relay.shutdown().await;
+}
+
+#[tokio::test]
+async fn relay_pool_skips_terminated_relays() {
+    let relay = TestRelay::run().await.unwrap();
+    let dead_relay = TestRelay::run().await.unwrap();
+    let dead_url = dead_relay.url();
+
+    let mut client = Client::new(vec![&relay.url()]).await.unwrap();
+    client.reconnect_options = ReconnectOptions {
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+        max_attempts: Some(0),
+        ..Default::default()
+    };
+    client.add_relay(&dead_url).await.unwrap();
+
+    let mut status = client.pool.relay(&dead_url).unwrap().status_watch();
+    dead_relay.shutdown().await;
+    wait_for_status(&mut status, |status| status == RelayStatus::Terminated).await;
+
+    // The message still reaches the relays listed after the terminated one
+    let req = ClientMessage::from(Req::new(None, vec![ReqFilter::new()]));
+    let sent = client
+        .pool
+        .send_to(&[dead_url.clone(), relay.url()], req)
+        .unwrap();
+    assert_eq!(sent, vec![relay.url()]);
+
+    assert!(client.subscribe(vec![ReqFilter::new()]).await.is_ok());
+
+    // Unknown relays are still rejected before anything is sent
+    let req = ClientMessage::from(Req::new(None, vec![ReqFilter::new()]));
+    assert!(matches!(
+        client
+            .pool
+            .send_to(&[relay.url(), "ws://127.0.0.1:1".to_string()], req),
+        Err(ClientError::RelayDoesNotExist)
+    ));
+
+    relay.shutdown().await;
+}
+
+#[test]
+fn req_filter_matches() {
+    let key = Keys::generate();
+    let pubkey = *GeneratePublicKey::new(key.secret_key()).public_key();
+    let other_pubkey = Keys::generate().public_key();
+    let referenced_id = "a".repeat(64);
+    let referenced_pubkey = "b".repeat(64);
+
+    let event = UnsignedEvent {
+        pubkey,
+        created_at: 1_700_000_000,
+        kind: Kind::TextNote,
+        tags: vec![
+            vec!["e".to_string(), referenced_id.clone()],
+            vec!["p".to_string(), referenced_pubkey.clone()],
+        ],
+        content: "tagged".to_string(),
+    };
+    let event = sign_event(&event, &key).unwrap();
+
+    let empty = ReqFilter::default();
+    assert!(empty.matches(&event));
+
+    let matching = [
+        ReqFilter {
+            ids: Some(vec![event.id]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            authors: Some(vec![other_pubkey, pubkey]),
+            kinds: Some(vec![Kind::Metadata, Kind::TextNote]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            tags: BTreeMap::from([('e', vec![referenced_id]), ('p', vec![referenced_pubkey])]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            since: Some(1_700_000_000),
+            until: Some(1_700_000_000),
+            limit: Some(0),
+            ..empty.clone()
+        },
+    ];
+    for filter in matching.iter() {
+        assert!(filter.matches(&event), "{:?}", filter);
+    }
+
+    let not_matching = [
+        ReqFilter {
+            ids: Some(vec![EventId::from_bytes([0; 32])]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            authors: Some(vec![pubkey]),
+            kinds: Some(vec![Kind::Metadata]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            tags: BTreeMap::from([('e', vec!["c".repeat(64)])]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            tags: BTreeMap::from([('p', vec![])]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            tags: BTreeMap::from([('t', vec!["nostr".to_string()])]),
+            ..empty.clone()
+        },
+        ReqFilter {
+            since: Some(1_700_000_001),
+            ..empty.clone()
+        },
+        ReqFilter {
+            until: Some(1_699_999_999),
+            ..empty.clone()
+        },
+    ];
+    for filter in not_matching.iter() {
+        assert!(!filter.matches(&event), "{:?}", filter);
+    }
+
+    assert!(ReqFilter::matches_any(
+        &[not_matching[0].clone(), matching[0].clone()],
+        &event
+    ));
+    assert!(!ReqFilter::matches_any(&not_matching, &event));
+    assert!(!ReqFilter::matches_any(&[], &event));
+}
+
+#[test]
+fn req_filter_generic_tags() {
+    let json = r##"{"kinds":[1],"#t":["nostr","rust"],"#d":["slug"],"#e":["id"],"#emoji":["x"],"#1":["x"],"custom":true}"##;
+    let filter: ReqFilter = serde_json::from_str(json).unwrap();
+
+    assert_eq!(filter.kinds, Some(vec![Kind::TextNote]));
+    assert_eq!(
+        filter.tag('t'),
+        Some(&["nostr".to_string(), "rust".to_string()][..])
+    );
+    assert_eq!(filter.tag('d'), Some(&["slug".to_string()][..]));
+    assert_eq!(filter.tag('e'), Some(&["id".to_string()][..]));
+    assert_eq!(
+        filter.e(),
+        Err(ReqFilterError::Parse(ParseError::InvalidBech32(
+            "id".to_string()
+        )))
+    );
+    assert_eq!(filter.p(), Ok(vec![]));
+    assert_eq!(filter.tags.len(), 3);
+
+    assert_eq!(
+        filter.to_json(),
+        serde_json::json!({"kinds":[1],"#d":["slug"],"#e":["id"],"#t":["nostr","rust"]})
+    );
+    assert_eq!(
+        serde_json::from_value::<ReqFilter>(serde_json::to_value(&filter).unwrap()).unwrap(),
+        filter
+    );
+
+    let key = Keys::generate();
+    let event = UnsignedEvent {
+        pubkey: key.public_key(),
+        created_at: Utc::now().timestamp(),
+        kind: Kind::TextNote,
+        tags: vec![
+            vec!["t".to_string(), "rust".to_string()],
+            vec!["d".to_string(), "slug".to_string()],
+            vec!["e".to_string(), "id".to_string(), "wss://relay".to_string()],
+        ],
+        content: "".to_string(),
+    };
+    let event = sign_event(&event, &key).unwrap();
+    assert!(filter.matches(&event));
+
+    let filter = ReqFilter {
+        tags: BTreeMap::from([('L', vec!["ISO-639-1".to_string()])]),
+        ..Default::default()
+    };
+    assert!(!filter.matches(&event));
+}
+
+#[test]
+fn req_filter_builder() {
+    let key = Keys::generate();
+    let pubkey = GeneratePublicKey::new(key.secret_key());
+    let event = text_note(&key, "builder", 1_700_000_000);
+    let note = ConvertKey::to_bech32_note(&event.id).unwrap();
+
+    let filter = ReqFilter::new()
+        .author(pubkey.bech32_public_key().unwrap())
+        .unwrap()
+        .authors([pubkey.hex_public_key().to_uppercase()])
+        .unwrap()
+        .id(&note)
+        .unwrap()
+        .pubkey(pubkey.hex_public_key())
+        .unwrap()
+        .kinds([1, 6])
+        .kind(Kind::Reaction)
+        .since(1_600_000_000)
+        .until(1_800_000_000)
+        .limit(50)
+        .hashtag("Nostr")
+        .identifier("slug");
+
+    assert_eq!(filter.authors, Some(vec![*pubkey.public_key(); 2]));
+    assert_eq!(filter.ids, Some(vec![event.id]));
+    assert_eq!(
+        filter.kinds,
+        Some(vec![Kind::TextNote, Kind::Repost, Kind::Reaction])
+    );
+    assert_eq!(filter.p(), Ok(vec![*pubkey.public_key()]));
+    assert_eq!(filter.tag('t'), Some(&["nostr".to_string()][..]));
+    assert_eq!(filter.tag('d'), Some(&["slug".to_string()][..]));
+    assert_eq!(
+        (filter.since, filter.until, filter.limit),
+        (Some(1_600_000_000), Some(1_800_000_000), Some(50))
+    );
+
+    let filter = ReqFilter::new()
+        .author(pubkey.hex_public_key())
+        .unwrap()
+        .id(event.id.to_hex())
+        .unwrap();
+    assert!(filter.matches(&event));
+
+    // Typed ids and pubkeys are taken as they are
+    let pubkeys = [key.public_key()];
+    let typed = ReqFilter::new()
+        .author(key.public_key())
+        .unwrap()
+        .ids([event.id])
+        .unwrap()
+        .pubkeys(pubkeys.iter())
+        .unwrap()
+        .event(event.id)
+        .unwrap();
+    assert_eq!(typed.authors, Some(vec![key.public_key()]));
+    assert_eq!(typed.ids, Some(vec![event.id]));
+    assert_eq!(typed.p(), Ok(vec![key.public_key()]));
+    assert_eq!(typed.e(), Ok(vec![event.id]));
+
+    assert_eq!(
+        ReqFilter::new().author("abc").unwrap_err(),
+        ReqFilterError::Parse(ParseError::InvalidHex("abc".to_string()))
+    );
+    assert_eq!(
+        ReqFilter::new().author(&note).unwrap_err(),
+        ReqFilterError::Parse(ParseError::WrongPrefix {
+            expected: "npub".to_string(),
+            found: "note".to_string()
+        })
+    );
+    assert_eq!(
+        ReqFilter::new()
+            .events([key.secret_key().to_bech32().unwrap()])
+            .unwrap_err(),
+        ReqFilterError::Parse(ParseError::WrongPrefix {
+            expected: "note".to_string(),
+            found: "nsec".to_string()
+        })
+    );
+    assert!(matches!(
+        ReqFilter::new().author("npub1invalid"),
+        Err(ReqFilterError::Parse(ParseError::InvalidBech32(_)))
+    ));
+    assert_eq!(
+        ReqFilter::new().custom_tag('1', ["x"]).unwrap_err(),
+        ReqFilterError::InvalidTagName('1')
+    );
+    assert_eq!(
+        ReqFilter::new().custom_tag('k', ["x"]).unwrap().tag('k'),
+        Some(&["x".to_string()][..])
+    );
+
+    // "e" and "p" values are parsed like the typed builders
+    let custom = ReqFilter::new()
+        .custom_tag('e', [&note])
+        .unwrap()
+        .custom_tag('p', [pubkey.bech32_public_key().unwrap()])
+        .unwrap();
+    assert_eq!(custom.e(), Ok(vec![event.id]));
+    assert_eq!(custom.tag('e'), Some(&[event.id.to_hex()][..]));
+    assert_eq!(custom.p(), Ok(vec![*pubkey.public_key()]));
+    assert_eq!(
+        ReqFilter::new().custom_tag('p', ["x"]).unwrap_err(),
+        ReqFilterError::Parse(ParseError::InvalidBech32("x".to_string()))
+    );
+}
+
+#[test]
+fn event_builder() {
+    let key = Keys::generate();
+    let pubkey = GeneratePublicKey::new(key.secret_key());
+
+    let note = EventBuilder::text_note("hello")
+        .tag(["t", "nostr"])
+        .tag(vec!["client".to_string(), "tests".to_string()])
+        .custom_created_at(1_700_000_000)
+        .build(&key)
+        .unwrap();
+    assert_eq!(note.kind, Kind::TextNote);
+    assert_eq!(note.content, "hello");
+    assert_eq!(&note.pubkey, pubkey.public_key());
+    assert_eq!(note.created_at, 1_700_000_000);
+    assert_eq!(note.tags, vec![vec!["t", "nostr"], vec!["client", "tests"]]);
+    assert!(verify_signature(&note.sig, &note.pubkey, &note.id).is_ok());
+
+    // The current time is used by default
+    let before = Utc::now().timestamp();
+    let now = EventBuilder::text_note("now").build(&key).unwrap();
+    assert!(now.created_at >= before && now.created_at <= Utc::now().timestamp());
+
+    let metadata = EventBuilder::metadata(&serde_json::json!({"name": "alice"}))
+        .build(&key)
+        .unwrap();
+    assert_eq!(metadata.kind, Kind::Metadata);
+    assert_eq!(metadata.content, r#"{"name":"alice"}"#);
+
+    let friend = GeneratePublicKey::new(Keys::generate().secret_key());
+    let contacts = EventBuilder::contact_list([
+        Contact::new(*friend.public_key()),
+        Contact {
+            relay_url: Some("wss://relay.example".to_string()),
+            petname: Some("me".to_string()),
+            ..Contact::new(*pubkey.public_key())
+        },
+    ])
+    .build(&key)
+    .unwrap();
+    assert_eq!(contacts.kind, Kind::ContactList);
+    assert_eq!(
+        contacts.tags,
+        vec![
+            vec!["p", friend.hex_public_key()],
+            vec!["p", pubkey.hex_public_key(), "wss://relay.example", "me"],
+        ]
+    );
+
+    let reaction = EventBuilder::reaction(&note, "+").build(&key).unwrap();
+    assert_eq!(reaction.kind, Kind::Reaction);
+    assert_eq!(reaction.content, "+");
+    assert_eq!(
+        reaction.tags,
+        vec![
+            vec!["e".to_string(), note.id.to_hex()],
+            vec!["p".to_string(), note.pubkey.to_hex()],
+            vec!["k".to_string(), "1".to_string()]
+        ]
+    );
+
+    let repost = EventBuilder::repost(&note, Some("wss://relay.example"))
+        .build(&key)
+        .unwrap();
+    assert_eq!(repost.kind, Kind::Repost);
+    assert_eq!(
+        serde_json::from_str::<SignedEvent>(&repost.content).unwrap(),
+        note
+    );
+    assert_eq!(
+        repost.tags[0],
+        vec!["e", &note.id.to_hex(), "wss://relay.example"]
+    );
+
+    let generic_repost = EventBuilder::repost(&reaction, None).build(&key).unwrap();
+    assert_eq!(generic_repost.kind, Kind::GenericRepost);
+    assert_eq!(generic_repost.tags[0], vec!["k", "7"]);
+
+    let deletion = EventBuilder::deletion([note.id, reaction.id], "oops")
+        .build(&key)
+        .unwrap();
+    assert_eq!(deletion.kind, Kind::EventDeletion);
+    assert_eq!(deletion.content, "oops");
+    assert_eq!(
+        deletion.tags,
+        vec![
+            vec!["e".to_string(), note.id.to_hex()],
+            vec!["e".to_string(), reaction.id.to_hex()]
+        ]
+    );
+}
+
+#[test]
+fn kind_classification() {
+    for number in [0, 1, 3, 7, 1059, 10002, 22242, 30023, 30078, 40000] {
+        assert_eq!(Kind::from(number).as_u16(), number);
+    }
+    assert_eq!(Kind::from(7), Kind::Reaction);
+    assert_eq!(Kind::from(30078), Kind::Custom(30078));
+    assert_eq!(Kind::GiftWrap.to_string(), "1059");
+
+    // Kinds are compared by number, whichever variant holds it
+    assert_eq!(Kind::Custom(1), Kind::TextNote);
+    assert_ne!(Kind::Custom(2), Kind::TextNote);
+    let mut kinds = vec![Kind::LongFormTextNote, Kind::Custom(3), Kind::TextNote];
+    kinds.sort();
+    assert_eq!(
+        kinds,
+        vec![Kind::TextNote, Kind::ContactList, Kind::LongFormTextNote]
+    );
+    let filter = ReqFilter {
+        kinds: Some(vec![Kind::Custom(1)]),
+        ..Default::default()
+    };
+    assert!(filter.matches(&text_note(&Keys::generate(), "custom", 1_700_000_000)));
+
+    assert!(Kind::TextNote.is_regular());
+    assert!(Kind::EventDeletion.is_regular());
+    assert!(Kind::Zap.is_regular());
+    assert!(!Kind::Metadata.is_regular());
+
+    assert!(Kind::Metadata.is_replaceable());
+    assert!(Kind::ContactList.is_replaceable());
+    assert!(Kind::RelayList.is_replaceable());
+    assert!(!Kind::TextNote.is_replaceable());
+
+    assert!(Kind::Authentication.is_ephemeral());
+    assert!(Kind::Custom(29999).is_ephemeral());
+    assert!(!Kind::Custom(30000).is_ephemeral());
+
+    assert!(Kind::LongFormTextNote.is_addressable());
+    assert!(!Kind::Custom(40000).is_addressable());
+    assert!(!Kind::Custom(40000).is_regular());
+
+    // Kinds are serialized as numbers
+    let event = text_note(&Keys::generate(), "kind", 1_700_000_000);
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["kind"], 1);
+    assert_eq!(
+        serde_json::from_value::<SignedEvent>(json).unwrap().kind,
+        Kind::TextNote
+    );
+    assert_eq!(
+        ReqFilter::new().kinds([1, 30078]).to_json(),
+        serde_json::json!({"kinds": [1, 30078]})
+    );
+}
+
+#[test]
+fn typed_keys_and_ids() {
+    let key = GeneratePrivateKey::new();
+    let secret_key = key.secret_key();
+    let public_key = secret_key.public_key();
+    let event = text_note(&Keys::new(secret_key.clone()), "typed", 1_700_000_000);
+
+    // hex and bech32 are both accepted
+    assert_eq!(
+        SecretKey::parse(key.hex_private_key()).as_ref(),
+        Ok(secret_key)
+    );
+    assert_eq!(
+        SecretKey::parse(&key.bech32_private_key().unwrap()).as_ref(),
+        Ok(secret_key)
+    );
+    assert_eq!(
+        PublicKey::parse(&public_key.to_bech32().unwrap()),
+        Ok(public_key)
+    );
+    assert_eq!(
+        public_key.to_hex().to_uppercase().parse::<PublicKey>(),
+        Ok(public_key)
+    );
+    assert_eq!(EventId::parse(&event.id.to_bech32().unwrap()), Ok(event.id));
+    assert_eq!(event.id.to_string().parse::<EventId>(), Ok(event.id));
+    assert_eq!(Signature::parse(&event.sig.to_hex()), Ok(event.sig));
+    assert_eq!(
+        ConvertKey::to_hex(&public_key.to_bech32().unwrap()).unwrap(),
+        public_key.to_hex()
+    );
+
+    // Invalid data is rejected when parsing
+    assert_eq!(
+        EventId::from_hex("zz"),
+        Err(ParseError::InvalidHex("zz".to_string()))
+    );
+    assert_eq!(
+        EventId::from_hex("abcd"),
+        Err(ParseError::InvalidLength {
+            expected: 32,
+            found: 2
+        })
+    );
+    assert_eq!(
+        Signature::from_hex(&"ab".repeat(32)),
+        Err(ParseError::InvalidLength {
+            expected: 64,
+            found: 32
+        })
+    );
+    assert_eq!(
+        PublicKey::parse(&key.bech32_private_key().unwrap()),
+        Err(ParseError::WrongPrefix {
+            expected: "npub".to_string(),
+            found: "nsec".to_string()
+        })
+    );
+    assert!(matches!(
+        SecretKey::from_hex(&"0".repeat(64)),
+        Err(ParseError::Secp256k1(_))
+    ));
+
+    // Everything is serialized as lowercase hex
+    let json = serde_json::to_value(&event).unwrap();
+    assert_eq!(json["id"], event.id.to_hex());
+    assert_eq!(json["pubkey"], public_key.to_hex());
+    assert_eq!(json["sig"], event.sig.to_hex());
+
+    let mut invalid = json.clone();
+    invalid["sig"] = serde_json::json!("abcd");
+    assert!(serde_json::from_value::<SignedEvent>(invalid).is_err());
+    let mut invalid = json;
+    invalid["pubkey"] = serde_json::json!(public_key.to_bech32().unwrap());
+    assert!(serde_json::from_value::<SignedEvent>(invalid).is_err());
+}
+
+#[test]
+fn verify_signed_events() {
+    let key = Keys::generate();
+    let event = text_note(&key, "verified", 1_700_000_000);
+    assert_eq!(event.verify(), Ok(()));
+
+    let mut forged = event.clone();
+    forged.content = "forged".to_string();
+    assert_eq!(
+        forged.verify(),
+        Err(VerifyError::IdMismatch {
+            expected: get_event_hash(&forged.to_unsigned_event()).unwrap(),
+            found: event.id,
+        })
+    );
+
+    // A valid id signed by another key
+    let mut forged = event.clone();
+    forged.sig = text_note(&key, "other", 1_700_000_000).sig;
+    assert_eq!(forged.verify(), Err(VerifyError::InvalidSignature));
+
+    let mut forged = event;
+    forged.pubkey = Keys::generate().public_key();
+    forged.id = get_event_hash(&forged.to_unsigned_event()).unwrap();
+    assert_eq!(forged.verify(), Err(VerifyError::InvalidSignature));
+}
+
+/// Raw websocket relay answering every REQ with the messages returned by `answer`, the
+/// connection is dropped when it returns None
+async fn scripted_relay<F>(answer: F) -> String
+where
+    F: Fn(String) -> Option<Vec<RelayMessage>> + Clone + Send + 'static,
+{
+    use futures::{SinkExt, StreamExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let answer = answer.clone();
+            tokio::spawn(async move {
+                let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+                while let Some(Ok(message)) = socket.next().await {
+                    let text = message.into_text().unwrap_or_default();
+                    if let Ok(ClientMessage::Req {
+                        subscription_id, ..
+                    }) = ClientMessage::from_json(&text)
+                    {
+                        let Some(messages) = answer(subscription_id) else {
+                            return;
+                        };
+                        for message in messages {
+                            socket.send(message.to_message()).await.unwrap();
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    url
+}
+
+/// Relay answering every REQ with the given events, even if they are invalid
+async fn forging_relay(events: Vec<SignedEvent>) -> String {
+    scripted_relay(move |subscription_id| {
+        let mut messages: Vec<RelayMessage> = events
+            .iter()
+            .map(|event| RelayMessage::Event {
+                subscription_id: subscription_id.clone(),
+                event: Box::new(event.clone()),
+            })
+            .collect();
+        messages.push(RelayMessage::Eose { subscription_id });
+        Some(messages)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn client_get_events_of_drops_forged_events() {
+    let key = Keys::generate();
+    let valid = text_note(&key, "valid", 1_700_000_000);
+    let mut forged_content = text_note(&key, "content", 1_700_000_001);
+    forged_content.content = "forged".to_string();
+    let mut forged_sig = text_note(&key, "sig", 1_700_000_002);
+    forged_sig.sig = valid.sig;
+
+    let url = forging_relay(vec![valid.clone(), forged_content, forged_sig]).await;
+    let mut client = Client::new(vec![&url]).await.unwrap();
+    assert!(client.verify_events);
+
+    let events = client.get_events_of(vec![ReqFilter::new()]).await.unwrap();
+    assert_eq!(events, vec![valid]);
+
+    client.verify_events = false;
+    let events = client.get_events_of(vec![ReqFilter::new()]).await.unwrap();
+    assert_eq!(events.len(), 3);
+}
+
+#[tokio::test]
+async fn client_forwards_unparsable_frames() {
+    use futures::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        for frame in [r#"["PING"]"#, r#"["NOTICE","hello"]"#] {
+            socket.send(Message::Text(frame.into())).await.unwrap();
+        }
+        std::future::pending::<()>().await;
+    });
+
+    // Listen before connecting so the frames sent on connection are not missed
+    let mut client = Client::new(vec![]).await.unwrap();
+    let mut notifications = client.notifications();
+    client.add_relay(&url).await.unwrap();
+    let mut received = Vec::new();
+    for _ in 0..2 {
+        let (_, message) = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+            .await
+            .expect("timed out waiting for the relay frames")
+            .unwrap();
+        received.push(message);
+    }
+
+    assert_eq!(
+        received,
+        vec![
+            Err(RelayMessageError::UnknownType("PING".to_string())),
+            Ok(RelayMessage::Notice {
+                message: "hello".to_string()
+            }),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn client_next_data_reports_lag() {
+    // More notices than the notification channel holds, followed by the EOSE
+    let flooding = scripted_relay(|subscription_id| {
+        let mut messages = vec![
+            RelayMessage::Notice {
+                message: "flood".to_string()
+            };
+            5000
+        ];
+        messages.push(RelayMessage::Eose { subscription_id });
+        Some(messages)
+    })
+    .await;
+
+    let mut client = Client::new(vec![&flooding]).await.unwrap();
+    let mut notifications = client.notifications();
+    let id = client.subscribe(vec![ReqFilter::default()]).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match notifications.recv().await {
+                Ok((_, Ok(RelayMessage::Eose { .. }))) => break,
+                Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(err) => panic!("{}", err),
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for EOSE");
+
+    assert!(matches!(
+        client.next_data().await,
+        Err(ClientError::Lagged(_))
+    ));
+    // The receiver resumes with the messages that are still queued
+    let data = client.next_data().await.unwrap();
+    assert_eq!(
+        data.last().unwrap().1,
+        Ok(RelayMessage::Eose {
+            subscription_id: id
+        })
+    );
+}
+
+#[test]
+fn errors_instead_of_panics() {
+    let key = Keys::generate();
+
+    let event = UnsignedEvent {
+        pubkey: key.public_key(),
+        created_at: i64::MAX,
+        kind: Kind::TextNote,
+        tags: vec![],
+        content: "".to_string(),
+    };
+    assert!(matches!(
+        serialize_event(&event),
+        Err(Error::InvalidEvent(_))
+    ));
+    assert!(matches!(
+        get_event_hash(&event),
+        Err(Error::InvalidEvent(_))
+    ));
+    assert!(matches!(
+        sign_event(&event, &key),
+        Err(Error::InvalidEvent(_))
+    ));
+
+    let note = text_note(&key, "note", 1_700_000_000);
+    let other = text_note(&key, "other", 1_700_000_000);
+    assert!(matches!(
+        verify_signature(&other.sig, &note.pubkey, &note.id),
+        Err(Error::Secp256k1(_))
+    ));
+
+    assert!(matches!(
+        ConvertKey::to_hex("not bech32"),
+        Err(Error::Bech32(_))
+    ));
+
+    // Errors of the other modules convert into the crate error
+    let error: Error = "abc".parse::<EventId>().unwrap_err().into();
+    assert!(matches!(error, Error::Parse(ParseError::InvalidHex(_))));
+    let mut forged = note;
+    forged.sig = other.sig;
+    let error: Error = forged.verify().unwrap_err().into();
+    assert!(matches!(
+        error,
+        Error::Verify(VerifyError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn batch_verify_events() {
+    let key = Keys::generate();
+    let mut events: Vec<SignedEvent> = (0..50)
+        .map(|i| text_note(&key, &i.to_string(), 1_700_000_000 + i))
+        .collect();
+
+    events[10].content = "forged".to_string();
+    events[20].sig = events[21].sig;
+
+    let results = verify_events(&events);
+    assert_eq!(results.len(), events.len());
+    for (i, result) in results.iter().enumerate() {
+        match i {
+            10 => assert!(matches!(result, Err(VerifyError::IdMismatch { .. }))),
+            20 => assert_eq!(result, &Err(VerifyError::InvalidSignature)),
+            _ => assert_eq!(result, &Ok(())),
+        }
+    }
+
+    assert!(verify_events(&[]).is_empty());
+}
+
+#[test]
+fn keys() {
+    let keys = Keys::generate();
+    let secret_key = keys.secret_key();
+
+    assert_eq!(keys.public_key(), secret_key.public_key());
+    assert_eq!(Keys::from_hex(&secret_key.to_hex()).unwrap(), keys);
+    assert_eq!(
+        Keys::from_nsec(&secret_key.to_bech32().unwrap()).unwrap(),
+        keys
+    );
+    assert_eq!(Keys::parse(&secret_key.to_hex()).unwrap(), keys);
+    assert_eq!(Keys::from_slice(secret_key.secret_bytes()).unwrap(), keys);
+    assert_eq!(Keys::new(secret_key.clone()), keys);
+    assert_ne!(Keys::generate(), keys);
+
+    // Secret material is erased from memory when dropped
+    fn erased_on_drop<T: zeroize::ZeroizeOnDrop>() {}
+    erased_on_drop::<Keys>();
+    erased_on_drop::<SecretKey>();
+    erased_on_drop::<ConversationKey>();
+    erased_on_drop::<nip44::MessageKeys>();
+
+    assert!(matches!(Keys::from_hex("abc"), Err(Error::Parse(_))));
+    assert!(matches!(
+        Keys::from_nsec(&keys.public_key().to_bech32().unwrap()),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+    assert!(Keys::from_slice(&[0; 32]).is_err());
+
+    // The secret key never shows up in debug output
+    let debug = format!("{:?}", keys);
+    assert!(debug.contains(&keys.public_key().to_hex()));
+    assert!(!debug.contains(&secret_key.to_hex()));
+    assert!(debug.contains("<redacted>"));
+    assert_eq!(format!("{:?}", secret_key), "SecretKey(<redacted>)");
+
+    let event = text_note(&keys, "keys", 1_700_000_000);
+    assert_eq!(event.pubkey, keys.public_key());
+    assert_eq!(event.verify(), Ok(()));
+}
+
+/// Signer holding its keys out of reach of the caller, like a remote bunker would
+struct RemoteSigner {
+    keys: Keys,
+    requests: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl NostrSigner for RemoteSigner {
+    async fn get_public_key(&self) -> Result<PublicKey, Error> {
+        Ok(self.keys.public_key())
+    }
+
+    async fn sign_event(&self, event: UnsignedEvent) -> Result<SignedEvent, Error> {
+        self.requests
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        self.keys.sign_event(event).await
+    }
+
+    async fn nip04_encrypt(&self, _: &PublicKey, _: &str) -> Result<String, Error> {
+        Err(Error::Signer("rejected by the user".to_string()))
+    }
+
+    async fn nip04_decrypt(&self, _: &PublicKey, _: &str) -> Result<String, Error> {
+        Err(Error::Signer("rejected by the user".to_string()))
+    }
+
+    async fn nip44_encrypt(&self, _: &PublicKey, _: &str) -> Result<String, Error> {
+        Err(Error::Signer("rejected by the user".to_string()))
+    }
+
+    async fn nip44_decrypt(&self, _: &PublicKey, _: &str) -> Result<String, Error> {
+        Err(Error::Signer("rejected by the user".to_string()))
+    }
+}
+
+#[tokio::test]
+async fn nostr_signer() {
+    let keys = Keys::generate();
+    let remote = Arc::new(RemoteSigner {
+        keys: keys.clone(),
+        requests: Default::default(),
+    });
+    let signer: Arc<dyn NostrSigner> = remote.clone();
+
+    assert_eq!(signer.get_public_key().await.unwrap(), keys.public_key());
+    assert!(matches!(
+        signer.nip04_encrypt(&keys.public_key(), "hello").await,
+        Err(Error::Signer(_))
+    ));
+
+    let note = EventBuilder::text_note("signed remotely")
+        .sign(&signer)
+        .await
+        .unwrap();
+    assert_eq!(note.pubkey, keys.public_key());
+    assert_eq!(note.verify(), Ok(()));
+
+    // Keys is the local implementation
+    let local = EventBuilder::text_note("signed locally")
+        .sign(&keys)
+        .await
+        .unwrap();
+    assert_eq!(local.verify(), Ok(()));
+
+    let relay = TestRelay::run().await.unwrap();
+    let mut client = Client::new(vec![&relay.url()]).await.unwrap();
+
+    assert!(matches!(
+        client
+            .publish_event_builder(EventBuilder::text_note("no signer"))
+            .await,
+        Err(ClientError::SignerNotConfigured)
+    ));
+
+    client.set_signer(signer);
+    let published = client
+        .publish_event_builder(EventBuilder::text_note("through the client"))
+        .await
+        .unwrap();
+    assert_eq!(remote.requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    let events = client
+        .get_events_of(vec![authors_filter(&keys)])
+        .await
+        .unwrap();
+    assert_eq!(events, vec![published]);
+}
+
+#[tokio::test]
+async fn sign_event_with_other_keys() {
+    let keys = Keys::generate();
+    let other = Keys::generate();
+    let event = EventBuilder::text_note("not mine").to_unsigned_event(&other.public_key());
+
+    assert!(matches!(
+        sign_event(&event, &keys),
+        Err(Error::InvalidEvent(_))
+    ));
+    assert!(matches!(
+        keys.sign_event(event.clone()).await,
+        Err(Error::InvalidEvent(_))
+    ));
+    assert_eq!(other.sign_event(event).await.unwrap().verify(), Ok(()));
+}
+
+#[test]
+fn nip19_tlv_entities() {
+    // Vector from the NIP-19 specification
+    let profile = Nip19Profile::from_bech32("nprofile1qqsrhuxx8l9ex335q7he0f09aej04zpazpl0ne2cgukyawd24mayt8gpp4mhxue69uhhytnc9e3k7mgpz4mhxue69uhkg6nzv9ejuumpv34kytnrdaksjlyr9p").unwrap();
+    assert_eq!(
+        profile.public_key.to_hex(),
+        "3bf0c63fcb93463407af97a5e5ee64fa883d107ef9e558472c4eb9aaaefa459d"
+    );
+    assert_eq!(
+        profile.relays,
+        vec!["wss://r.x.com", "wss://djbas.sadkb.com"]
+    );
+    assert_eq!(
+        Nip19Profile::from_bech32(&profile.to_bech32().unwrap()).unwrap(),
+        profile
+    );
+
+    let keys = Keys::generate();
+    let event = text_note(&keys, "nevent", 1_700_000_000);
+    let nevent = Nip19Event {
+        event_id: event.id,
+        author: Some(event.pubkey),
+        kind: Some(event.kind),
+        relays: vec!["wss://relay.damus.io".to_string()],
+    };
+    let encoded = nevent.to_bech32().unwrap();
+    assert!(encoded.starts_with("nevent1"));
+    assert_eq!(Nip19Event::from_bech32(&encoded).unwrap(), nevent);
+
+    let bare = Nip19Event::new(event.id);
+    assert_eq!(
+        Nip19Event::from_bech32(&bare.to_bech32().unwrap()).unwrap(),
+        bare
+    );
+
+    let mut address = Nip19Address::new(Kind::LongFormTextNote, keys.public_key(), "my-article");
+    address.relays.push("wss://nos.lol".to_string());
+    let naddr = address.to_bech32().unwrap();
+    assert!(naddr.starts_with("naddr1"));
+    assert_eq!(Nip19Address::from_bech32(&naddr).unwrap(), address);
+
+    let relay = Nip19Relay::new("wss://relay.nostr.band");
+    let nrelay = relay.to_bech32().unwrap();
+    assert_eq!(Nip19Relay::from_bech32(&nrelay).unwrap(), relay);
+
+    // The prefix is checked before the payload is decoded
+    assert!(matches!(
+        Nip19Event::from_bech32(&naddr),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+    assert!(matches!(
+        Nip19Profile::from_bech32(&keys.public_key().to_bech32().unwrap()),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+
+    // naddr requires an author and a kind
+    let truncated = bech32::encode(
+        "naddr",
+        bech32::ToBase32::to_base32(&[0u8, 2, b'i', b'd']),
+        bech32::Variant::Bech32,
+    )
+    .unwrap();
+    assert!(matches!(
+        Nip19Address::from_bech32(&truncated),
+        Err(Error::Nip19(Nip19Error::MissingField("author")))
+    ));
+
+    let cut = bech32::encode(
+        "nprofile",
+        bech32::ToBase32::to_base32(&[0u8, 32, 1, 2]),
+        bech32::Variant::Bech32,
+    )
+    .unwrap();
+    assert!(matches!(
+        Nip19Profile::from_bech32(&cut),
+        Err(Error::Nip19(Nip19Error::TruncatedTlv))
+    ));
+
+    let long_relay = Nip19Profile {
+        public_key: keys.public_key(),
+        relays: vec!["x".repeat(256)],
+    };
+    assert!(matches!(
+        long_relay.to_bech32(),
+        Err(Error::Nip19(Nip19Error::InvalidField("relay")))
+    ));
+}
+
+#[test]
+fn nip19_decoding() {
+    let keys = Keys::generate();
+    let npub = keys.public_key().to_bech32().unwrap();
+    let nsec = keys.secret_key().to_bech32().unwrap();
+    let event = text_note(&keys, "nip19", 1_700_000_000);
+    let note = ConvertKey::to_bech32_note(&event.id).unwrap();
+    let nprofile = Nip19Profile::new(keys.public_key()).to_bech32().unwrap();
+
+    assert_eq!(
+        ConvertKey::decode(&npub).unwrap(),
+        Nip19::Npub(keys.public_key())
+    );
+    assert_eq!(
+        ConvertKey::decode(&nsec).unwrap(),
+        Nip19::Nsec(keys.secret_key().clone())
+    );
+    assert_eq!(ConvertKey::decode(&note).unwrap(), Nip19::Note(event.id));
+    assert_eq!(
+        ConvertKey::decode(&nprofile).unwrap(),
+        Nip19::Profile(Nip19Profile::new(keys.public_key()))
+    );
+    for value in [&npub, &nsec, &note, &nprofile] {
+        assert_eq!(
+            &Nip19::from_bech32(value).unwrap().to_bech32().unwrap(),
+            value
+        );
+    }
+
+    assert_eq!(
+        ConvertKey::npub_to_hex(&npub).unwrap(),
+        keys.public_key().to_hex()
+    );
+    assert_eq!(
+        ConvertKey::nsec_to_hex(&nsec).unwrap(),
+        keys.secret_key().to_hex()
+    );
+    assert_eq!(ConvertKey::note_to_hex(&note).unwrap(), event.id.to_hex());
+
+    // A secret key can't be passed where a public key is expected
+    assert!(matches!(
+        ConvertKey::npub_to_hex(&nsec),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+    assert!(matches!(
+        ConvertKey::nsec_to_hex(&npub),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+    assert!(matches!(
+        ConvertKey::note_to_hex(&npub),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+
+    // TLV payloads are not raw keys
+    assert!(matches!(
+        ConvertKey::to_hex(&nprofile),
+        Err(Error::Parse(ParseError::WrongPrefix { .. }))
+    ));
+
+    let unknown = bech32::encode(
+        "nfoo",
+        bech32::ToBase32::to_base32(&[0u8; 32]),
+        bech32::Variant::Bech32,
+    )
+    .unwrap();
+    assert!(matches!(
+        ConvertKey::decode(&unknown),
+        Err(Error::Nip19(Nip19Error::UnknownPrefix(_)))
+    ));
+}
+
+#[test]
+fn nostr_uri() {
+    let keys = Keys::generate();
+    let event = text_note(&keys, "nostr uri", 1_700_000_000);
+    let nevent = Nip19Event {
+        event_id: event.id,
+        author: Some(keys.public_key()),
+        kind: Some(Kind::TextNote),
+        relays: vec!["wss://relay.damus.io".to_string()],
+    };
+    let naddr = Nip19Address::new(Kind::LongFormTextNote, keys.public_key(), "article");
+
+    for entity in [
+        Nip19::Npub(keys.public_key()),
+        Nip19::Note(event.id),
+        Nip19::Event(nevent),
+        Nip19::Address(naddr),
+    ] {
+        let uri = NostrUri::new(entity.clone()).unwrap().to_uri().unwrap();
+        assert_eq!(uri, format!("nostr:{}", entity.to_bech32().unwrap()));
+
+        let parsed: NostrUri = uri.parse().unwrap();
+        assert_eq!(parsed.into_entity(), entity);
+    }
+
+    let npub = keys.public_key().to_bech32().unwrap();
+    assert_eq!(
+        NostrUri::parse(&format!("NOSTR:{npub}")).unwrap().entity(),
+        &Nip19::Npub(keys.public_key())
+    );
+
+    // Secret keys are never shared in URIs
+    let nsec = keys.secret_key().to_bech32().unwrap();
+    assert!(matches!(
+        NostrUri::parse(&format!("nostr:{nsec}")),
+        Err(Error::Nip21(Nip21Error::SecretKey))
+    ));
+    assert!(matches!(
+        NostrUri::try_from(Nip19::Nsec(keys.secret_key().clone())),
+        Err(Error::Nip21(Nip21Error::SecretKey))
+    ));
+
+    assert!(matches!(
+        NostrUri::parse(&npub),
+        Err(Error::Nip21(Nip21Error::MissingScheme))
+    ));
+    assert!(matches!(
+        NostrUri::parse("nostr:npub1invalid"),
+        Err(Error::Bech32(_))
+    ));
+}
+
+#[tokio::test]
+async fn nip04_direct_messages() {
+    // Vector shared by nostr-tools and rust-nostr
+    let sender =
+        Keys::from_hex("91ba716fa9e7ea2fcbad360cf4f8e0d312f73984da63d90f524ad61a6a1e7dbe").unwrap();
+    let receiver =
+        Keys::from_hex("96f6fa197aa07477ab88f6981118466ae3a982faab8ad5db9d5426870c73d220").unwrap();
+    assert_eq!(
+        nip04::decrypt(
+            receiver.secret_key(),
+            &sender.public_key(),
+            "zJxfaJ32rN5Dg1ODjOlEew==?iv=EV5bUjcc4OX2Km/zPp4ndQ=="
+        )
+        .unwrap(),
+        "nanana"
+    );
+
+    let content = nip04::encrypt(sender.secret_key(), &receiver.public_key(), "hello bob").unwrap();
+    assert!(content.contains("?iv="));
+    assert_eq!(
+        nip04::decrypt(receiver.secret_key(), &sender.public_key(), &content).unwrap(),
+        "hello bob"
+    );
+    assert_eq!(
+        nip04::decrypt(sender.secret_key(), &receiver.public_key(), &content).unwrap(),
+        "hello bob"
+    );
+    assert!(matches!(
+        nip04::decrypt(
+            Keys::generate().secret_key(),
+            &sender.public_key(),
+            &content
+        ),
+        Err(Error::Nip04(Nip04Error::Decryption))
+    ));
+    assert!(matches!(
+        nip04::decrypt(receiver.secret_key(), &sender.public_key(), "no iv"),
+        Err(Error::Nip04(Nip04Error::InvalidContent))
+    ));
+    assert!(matches!(
+        nip04::decrypt(receiver.secret_key(), &sender.public_key(), "!!?iv=!!"),
+        Err(Error::Nip04(Nip04Error::InvalidBase64))
+    ));
+
+    let message = EventBuilder::encrypted_direct_message(&sender, &receiver.public_key(), "hi")
+        .await
+        .unwrap();
+    assert_eq!(message.pubkey, sender.public_key());
+    assert_eq!(message.kind, Kind::EncryptedDirectMessage);
+    assert_eq!(
+        message.tags,
+        vec![vec!["p".to_string(), receiver.public_key().to_hex()]]
+    );
+    assert_eq!(
+        nip04::decrypt(receiver.secret_key(), &message.pubkey, &message.content).unwrap(),
+        "hi"
+    );
+
+    // Keys encrypts through the signer trait too
+    let signer: Arc<dyn NostrSigner> = Arc::new(receiver.clone());
+    let reply = signer
+        .nip04_encrypt(&sender.public_key(), "hi alice")
+        .await
+        .unwrap();
+    assert_eq!(
+        sender
+            .nip04_decrypt(&receiver.public_key(), &reply)
+            .await
+            .unwrap(),
+        "hi alice"
+    );
+
+    // Direct messages can be sent with any signer
+    let message =
+        EventBuilder::encrypted_direct_message(signer.as_ref(), &sender.public_key(), "hi again")
+            .await
+            .unwrap();
+    assert_eq!(message.pubkey, receiver.public_key());
+    assert_eq!(
+        nip04::decrypt(sender.secret_key(), &message.pubkey, &message.content).unwrap(),
+        "hi again"
+    );
+}
+
+fn nip44_vectors() -> serde_json::Value {
+    let vectors = include_str!("data/nip44.vectors.json");
+    serde_json::from_str::<serde_json::Value>(vectors).unwrap()["v2"].take()
+}
+
+fn hex32(value: &serde_json::Value) -> [u8; 32] {
+    hex::decode(value.as_str().unwrap())
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+#[test]
+fn nip44_valid_vectors() {
+    let vectors = nip44_vectors();
+    let valid = &vectors["valid"];
+
+    for vector in valid["get_conversation_key"].as_array().unwrap() {
+        let secret_key = SecretKey::from_hex(vector["sec1"].as_str().unwrap()).unwrap();
+        let public_key = PublicKey::from_hex(vector["pub2"].as_str().unwrap()).unwrap();
+
+        assert_eq!(
+            ConversationKey::derive(&secret_key, &public_key).as_bytes(),
+            &hex32(&vector["conversation_key"]),
+            "{}",
+            vector["note"]
+        );
+    }
+
+    for vector in valid["calc_padded_len"].as_array().unwrap() {
+        let len = vector[0].as_u64().unwrap() as usize;
+        assert_eq!(
+            nip44::calc_padded_len(len),
+            vector[1].as_u64().unwrap() as usize
+        );
+    }
+
+    for vector in valid["encrypt_decrypt"].as_array().unwrap() {
+        let sec1 = SecretKey::from_hex(vector["sec1"].as_str().unwrap()).unwrap();
+        let sec2 = SecretKey::from_hex(vector["sec2"].as_str().unwrap()).unwrap();
+        let plaintext = vector["plaintext"].as_str().unwrap();
+        let payload = vector["ciphertext"].as_str().unwrap();
+
+        let conversation_key = ConversationKey::derive(&sec1, &sec2.public_key());
+        assert_eq!(
+            conversation_key,
+            ConversationKey::from_bytes(hex32(&vector["conversation_key"]))
+        );
+        assert_eq!(
+            conversation_key,
+            ConversationKey::derive(&sec2, &sec1.public_key())
+        );
+        assert_eq!(
+            nip44::encrypt_with_conversation_key(
+                &conversation_key,
+                plaintext,
+                &hex32(&vector["nonce"])
+            )
+            .unwrap(),
+            payload
+        );
+        assert_eq!(
+            nip44::decrypt(&sec2, &sec1.public_key(), payload).unwrap(),
+            plaintext
+        );
+    }
+
+    for vector in valid["encrypt_decrypt_long_msg"].as_array().unwrap() {
+        let conversation_key = ConversationKey::from_bytes(hex32(&vector["conversation_key"]));
+        let plaintext = vector["letter"]
+            .as_str()
+            .unwrap()
+            .repeat(vector["repeat"].as_u64().unwrap() as usize);
+        let nonce = hex32(&vector["nonce"]);
+
+        let result = nip44::encrypt_with_conversation_key(&conversation_key, &plaintext, &nonce);
+
+        // Messages are limited to 65535 bytes, not characters
+        if plaintext.len() > 65535 {
+            assert!(matches!(
+                result,
+                Err(Error::Nip44(Nip44Error::InvalidMessageLength(_)))
+            ));
+        } else {
+            let payload = result.unwrap();
+            assert_eq!(
+                hex::encode(Sha256::digest(&payload)),
+                vector["ciphertext_checksum"].as_str().unwrap()
+            );
+
+            let decrypted =
+                nip44::decrypt_with_conversation_key(&conversation_key, &payload).unwrap();
+            assert_eq!(
+                Sha256::digest(decrypted.as_bytes()),
+                Sha256::digest(plaintext.as_bytes())
+            );
+        }
+    }
+}
+
+#[test]
+fn nip44_message_keys() {
+    let vectors = nip44_vectors();
+
+    // The message keys must authenticate and decrypt the reference payloads on their own
+    for vector in vectors["valid"]["encrypt_decrypt"].as_array().unwrap() {
+        let conversation_key = ConversationKey::from_bytes(hex32(&vector["conversation_key"]));
+        let nonce = hex32(&vector["nonce"]);
+        let keys = nip44::get_message_keys(&conversation_key, &nonce);
+        assert_eq!(keys, nip44::get_message_keys(&conversation_key, &nonce));
+
+        let payload = STANDARD
+            .decode(vector["ciphertext"].as_str().unwrap())
+            .unwrap();
+        assert_eq!(payload[1..33], nonce);
+        let (ciphertext, mac) = payload[33..].split_at(payload.len() - 33 - 32);
+
+        let mut hmac = Hmac::<Sha256>::new_from_slice(keys.hmac_key()).unwrap();
+        hmac.update(&nonce);
+        hmac.update(ciphertext);
+        hmac.verify_slice(mac).unwrap();
+
+        let mut padded = ciphertext.to_vec();
+        ChaCha20::new(keys.chacha_key().into(), keys.chacha_nonce().into())
+            .apply_keystream(&mut padded);
+        let plaintext = vector["plaintext"].as_str().unwrap();
+        assert_eq!(
+            u16::from_be_bytes([padded[0], padded[1]]) as usize,
+            plaintext.len()
+        );
+        assert_eq!(&padded[2..2 + plaintext.len()], plaintext.as_bytes());
+        assert!(padded[2 + plaintext.len()..].iter().all(|byte| *byte == 0));
+    }
+
+    // Each nonce expands to different keys
+    let conversation_key = ConversationKey::from_bytes([1; 32]);
+    let first = nip44::get_message_keys(&conversation_key, &[0; 32]);
+    let second = nip44::get_message_keys(&conversation_key, &[1; 32]);
+    assert_ne!(first.chacha_key(), second.chacha_key());
+    assert_ne!(first.chacha_nonce(), second.chacha_nonce());
+    assert_ne!(first.hmac_key(), second.hmac_key());
+}
+
+#[test]
+fn nip44_invalid_vectors() {
+    let vectors = nip44_vectors();
+    let invalid = &vectors["invalid"];
+    let conversation_key = ConversationKey::from_bytes([1; 32]);
+
+    for len in invalid["encrypt_msg_lengths"].as_array().unwrap() {
+        let plaintext = "a".repeat(len.as_u64().unwrap() as usize);
+        assert!(matches!(
+            nip44::encrypt_with_conversation_key(&conversation_key, &plaintext, &[0; 32]),
+            Err(Error::Nip44(Nip44Error::InvalidMessageLength(_)))
+        ));
+    }
+
+    for len in invalid["decrypt_msg_lengths"].as_array().unwrap() {
+        let payload = "a".repeat(len.as_u64().unwrap() as usize);
+        assert!(nip44::decrypt_with_conversation_key(&conversation_key, &payload).is_err());
+    }
+
+    // Invalid secret or public keys are rejected before the key exchange
+    for vector in invalid["get_conversation_key"].as_array().unwrap() {
+        let secret_key = SecretKey::from_hex(vector["sec1"].as_str().unwrap());
+        let public_key = PublicKey::from_hex(vector["pub2"].as_str().unwrap());
+        assert!(
+            secret_key.is_err() || public_key.is_err(),
+            "{}",
+            vector["note"]
+        );
+    }
+
+    for vector in invalid["decrypt"].as_array().unwrap() {
+        let conversation_key = ConversationKey::from_bytes(hex32(&vector["conversation_key"]));
+        let result = nip44::decrypt_with_conversation_key(
+            &conversation_key,
+            vector["ciphertext"].as_str().unwrap(),
+        );
+
+        let note = vector["note"].as_str().unwrap();
+        match note {
+            "invalid MAC" => assert!(matches!(result, Err(Error::Nip44(Nip44Error::InvalidMac)))),
+            "invalid padding" => assert!(matches!(
+                result,
+                Err(Error::Nip44(Nip44Error::InvalidPadding))
+            )),
+            _ => assert!(result.is_err(), "{}", note),
+        }
+    }
+}
+
+#[tokio::test]
+async fn nip44_keys() {
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+
+    let payload = alice.nip44_encrypt(&bob.public_key(), "hello bob").unwrap();
+    assert_ne!(
+        payload,
+        alice.nip44_encrypt(&bob.public_key(), "hello bob").unwrap()
+    );
+    assert_eq!(
+        bob.nip44_decrypt(&alice.public_key(), &payload).unwrap(),
+        "hello bob"
+    );
+    assert!(Keys::generate()
+        .nip44_decrypt(&alice.public_key(), &payload)
+        .is_err());
+
+    let signer: Arc<dyn NostrSigner> = Arc::new(bob.clone());
+    let reply = signer
+        .nip44_encrypt(&alice.public_key(), "hello alice")
+        .await
+        .unwrap();
+    assert_eq!(
+        alice.nip44_decrypt(&bob.public_key(), &reply).unwrap(),
+        "hello alice"
+    );
+}
+
+#[test]
+fn nip59_gift_wrap() {
+    let alice = Keys::generate();
+    let bob = Keys::generate();
+    let now = Utc::now().timestamp();
+
+    let rumor = EventBuilder::private_direct_message(&bob.public_key(), "hello bob")
+        .to_unsigned_event(&alice.public_key());
+    assert_eq!(rumor.kind, Kind::PrivateDirectMessage);
+    assert_eq!(
+        rumor.tags,
+        vec![vec!["p".to_string(), bob.public_key().to_hex()]]
+    );
+
+    let gift_wrap = nip59::gift_wrap(&alice, &bob.public_key(), &rumor).unwrap();
+    assert_eq!(gift_wrap.kind, Kind::GiftWrap);
+    assert_ne!(gift_wrap.pubkey, alice.public_key());
+    assert_eq!(
+        gift_wrap.tags,
+        vec![vec!["p".to_string(), bob.public_key().to_hex()]]
+    );
+    assert!(gift_wrap.created_at <= now && gift_wrap.created_at > now - 2 * 24 * 60 * 60);
+    assert_eq!(gift_wrap.verify(), Ok(()));
+
+    let unwrapped = nip59::unwrap_gift_wrap(&bob, &gift_wrap).unwrap();
+    assert_eq!(
+        unwrapped,
+        UnwrappedGift {
+            sender: alice.public_key(),
+            rumor: rumor.clone(),
+        }
+    );
+
+    // Only the receiver can open it
+    assert!(matches!(
+        nip59::unwrap_gift_wrap(&alice, &gift_wrap),
+        Err(Error::Nip44(Nip44Error::InvalidMac))
+    ));
+    assert!(matches!(
+        nip59::unwrap_gift_wrap(&bob, &text_note(&alice, "not a gift wrap", now)),
+        Err(Error::Nip59(Nip59Error::WrongKind { .. }))
+    ));
+
+    // A tampered gift wrap fails verification
+    let mut tampered = gift_wrap.clone();
+    tampered.created_at += 1;
+    assert!(matches!(
+        nip59::unwrap_gift_wrap(&bob, &tampered),
+        Err(Error::Verify(VerifyError::IdMismatch { .. }))
+    ));
+
+    // Mallory can't seal a rumor in the name of Alice
+    let mallory = Keys::generate();
+    assert!(matches!(
+        nip59::seal(&mallory, &bob.public_key(), &rumor),
+        Err(Error::Nip59(Nip59Error::SenderMismatch))
+    ));
+
+    let forged_seal = sign_event(
+        &UnsignedEvent {
+            content: mallory
+                .nip44_encrypt(&bob.public_key(), &serde_json::to_string(&rumor).unwrap())
+                .unwrap(),
+            created_at: now,
+            kind: Kind::Seal,
+            pubkey: mallory.public_key(),
+            tags: Vec::new(),
+        },
+        &mallory,
+    )
+    .unwrap();
+    let ephemeral = Keys::generate();
+    let forged = sign_event(
+        &UnsignedEvent {
+            content: ephemeral
+                .nip44_encrypt(
+                    &bob.public_key(),
+                    &serde_json::to_string(&forged_seal).unwrap(),
+                )
+                .unwrap(),
+            created_at: now,
+            kind: Kind::GiftWrap,
+            pubkey: ephemeral.public_key(),
+            tags: vec![vec!["p".to_string(), bob.public_key().to_hex()]],
+        },
+        &ephemeral,
+    )
+    .unwrap();
+    assert!(matches!(
+        nip59::unwrap_gift_wrap(&bob, &forged),
+        Err(Error::Nip59(Nip59Error::SenderMismatch))
+    ));
+}
+
+#[test]
+fn nip06_mnemonic() {
+    // Vectors from the NIP-06 specification
+    let vectors = [
+        (
+            "leader monkey parrot ring guide accident before fence cannon height naive bean",
+            "7f7ff03d123792d6ac594bfa67bf6d0c0ab55b6b1fdb6249303fe861f1ccba9a",
+            "17162c921dc4d2518f9a101db33695df1afb56ab82f5ff3e5da6eec3ca5cd917",
+        ),
+        (
+            "what bleak badge arrange retreat wolf trade produce cricket blur garlic valid proud rude strong choose busy staff weather area salt hollow arm fade",
+            "c15d739894c81a2fcfd3a2df85a0d2c0dbc47a280d092799f144d73d7ae78add",
+            "d41b22899549e1f3d335a31002cfd382174006e166d3e658e3a5eecdb6463573",
+        ),
+    ];
+
+    for (mnemonic, secret_key, public_key) in vectors {
+        let key = GeneratePrivateKey::from_mnemonic(mnemonic, None, 0).unwrap();
+        assert_eq!(key.hex_private_key(), secret_key);
+        assert_eq!(key.secret_key().public_key().to_hex(), public_key);
+    }
+
+    let (mnemonic, secret_key, _) = vectors[0];
+    let other_account = nip06::derive_secret_key(mnemonic, None, 1).unwrap();
+    let with_passphrase = nip06::derive_secret_key(mnemonic, Some("passphrase"), 0).unwrap();
+    assert_ne!(other_account.to_hex(), secret_key);
+    assert_ne!(with_passphrase.to_hex(), secret_key);
+    assert_ne!(other_account, with_passphrase);
+    assert_eq!(
+        nip06::derive_secret_key(mnemonic, Some(""), 0)
+            .unwrap()
+            .to_hex(),
+        secret_key
+    );
+
+    let generated = nip06::generate_mnemonic(24).unwrap();
+    assert_eq!(generated.split(' ').count(), 24);
+    assert_eq!(
+        GeneratePrivateKey::from_mnemonic(&generated, None, 0)
+            .unwrap()
+            .secret_key(),
+        &nip06::derive_secret_key(&generated, None, 0).unwrap()
+    );
+
+    assert!(matches!(
+        GeneratePrivateKey::from_mnemonic("leader monkey parrot", None, 0),
+        Err(Error::Nip06(Nip06Error::Mnemonic(_)))
+    ));
+    // The last word carries the checksum
+    assert!(matches!(
+        GeneratePrivateKey::from_mnemonic(&mnemonic.replace("bean", "beach"), None, 0),
+        Err(Error::Nip06(Nip06Error::Mnemonic(_)))
+    ));
+    assert!(matches!(
+        nip06::derive_secret_key(mnemonic, None, 1 << 31),
+        Err(Error::Nip06(Nip06Error::InvalidAccount(_)))
+    ));
+    assert!(matches!(
+        nip06::generate_mnemonic(13),
+        Err(Error::Nip06(Nip06Error::Mnemonic(_)))
+    ));
+}
+
+#[tokio::test]
+async fn client_get_events_of_never_hangs() {
+    let key = Keys::generate();
+    let event = text_note(&key, "closed", 1_700_000_000);
+
+    // A relay answering CLOSED is done, its events are kept
+    let closing = scripted_relay(move |subscription_id| {
+        Some(vec![
+            RelayMessage::Event {
+                subscription_id: subscription_id.clone(),
+                event: Box::new(event.clone()),
+            },
+            RelayMessage::Closed {
+                subscription_id,
+                message: "auth-required: we only serve authenticated users".to_string(),
+            },
+        ])
+    })
+    .await;
+    let mut client = Client::new(vec![&closing]).await.unwrap();
+    client.get_events_timeout = Duration::from_secs(30);
+    let events = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.get_events_of(vec![ReqFilter::new()]),
+    )
+    .await
+    .expect("CLOSED should end get_events_of")
+    .unwrap();
+    assert_eq!(events.len(), 1);
+
+    // A relay that never answers is given up on after the timeout
+    let silent = scripted_relay(|_| Some(Vec::new())).await;
+    let mut client = Client::new(vec![&silent]).await.unwrap();
+    let events = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.get_events_of_with_timeout(vec![ReqFilter::new()], Duration::from_millis(200)),
+    )
+    .await
+    .expect("the timeout should end get_events_of")
+    .unwrap();
+    assert!(events.is_empty());
+
+    // A relay dropping the connection and giving up reconnecting is not waited for
+    let dropping = scripted_relay(|_| None).await;
+    let mut client = Client::new(vec![]).await.unwrap();
+    client.reconnect_options = ReconnectOptions {
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_millis(10),
+        max_attempts: Some(0),
+        ..Default::default()
+    };
+    client.get_events_timeout = Duration::from_secs(30);
+    client.add_relay(&dropping).await.unwrap();
+    let events = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.get_events_of(vec![ReqFilter::new()]),
+    )
+    .await
+    .expect("a terminated relay should end get_events_of")
+    .unwrap();
+    assert!(events.is_empty());
+    assert_eq!(
+        client.relay_status(&dropping),
+        Some(RelayStatus::Terminated)
+    );
+
+    // The terminated relay is skipped by the next calls
+    let relay = TestRelay::run().await.unwrap();
+    client.add_relay(&relay.url()).await.unwrap();
+    let event = text_note(&key, "after", 1_700_000_000);
+    client.publish_event(&event).await.unwrap();
+    let events = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.get_events_of(vec![authors_filter(&key)]),
+    )
+    .await
+    .expect("a terminated relay should not be waited for")
+    .unwrap();
+    assert_eq!(events, vec![event]);
+
+    relay.shutdown().await;
 }